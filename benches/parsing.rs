@@ -0,0 +1,59 @@
+//! Throughput benchmarks for turning GTP logs into commands.
+
+use std::fmt::Write;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use go_text_protocol::{parse, parse_borrowed, CommandReader, RawCommand};
+
+const COLUMNS: &str = "ABCDEFGHJKLMNOPQRST";
+
+/// Generate something which looks like the controller's side of a 19x19
+/// self-play game, with the occasional comment and blank line thrown in.
+fn game_log(num_moves: usize) -> String {
+    let mut log = String::from("1 boardsize 19\n2 clear_board\n3 komi 7.5\n\n");
+    let columns: Vec<char> = COLUMNS.chars().collect();
+
+    for i in 0..num_moves {
+        let id = i + 4;
+        let color = if i % 2 == 0 { "black" } else { "white" };
+        let column = columns[(i * 7) % columns.len()];
+        let row = (i * 11) % 19 + 1;
+
+        if i % 50 == 0 {
+            writeln!(log, "# move {}", i).unwrap();
+        }
+
+        if i % 10 == 9 {
+            writeln!(log, "{} genmove {}", id, color).unwrap();
+        } else {
+            writeln!(log, "{} play {} {}{}", id, color, column, row).unwrap();
+        }
+    }
+
+    log.push_str("final_score\nquit\n");
+    log
+}
+
+fn parse_lines(c: &mut Criterion) {
+    let log = game_log(10_000);
+
+    let mut group = c.benchmark_group("game log");
+    group.throughput(Throughput::Bytes(log.len() as u64));
+
+    group.bench_function("parse", |b| {
+        b.iter(|| log.lines().filter_map(|line| parse::<RawCommand>(line).ok()).count())
+    });
+
+    group.bench_function("parse_borrowed", |b| {
+        b.iter(|| log.lines().filter_map(|line| parse_borrowed(line).ok()).count())
+    });
+
+    group.bench_function("CommandReader", |b| {
+        b.iter(|| CommandReader::new(log.as_bytes()).filter_map(|cmd| cmd.ok()).count())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse_lines);
+criterion_main!(benches);
@@ -0,0 +1,113 @@
+//! A tiny GTP engine used by the integration tests as a stand-in for a real
+//! engine like GNU Go.
+//!
+//! It doesn't know anything about the rules of Go, it just keeps track of
+//! which points are occupied and always plays on the first empty one.
+
+extern crate go_text_protocol;
+
+use std::io;
+
+use go_text_protocol::{Color, Move, Vertex};
+use go_text_protocol::engine::{CommandResult, Engine, run_session};
+
+struct FakeEngine {
+    size: u8,
+    moves: Vec<Move>,
+}
+
+impl FakeEngine {
+    fn is_occupied(&self, vertex: Vertex) -> bool {
+        vertex.coords().is_some() && self.moves.iter().any(|mv| mv.vertex == vertex)
+    }
+
+    fn first_empty(&self) -> Vertex {
+        for row in 0..self.size {
+            for column in 0..self.size {
+                let vertex = Vertex::Point { column, row };
+                if !self.is_occupied(vertex) {
+                    return vertex;
+                }
+            }
+        }
+
+        Vertex::Pass
+    }
+}
+
+impl Engine for FakeEngine {
+    fn name(&self) -> String {
+        "Fake Engine".to_string()
+    }
+
+    fn version(&self) -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+
+    fn boardsize(&mut self, size: u32) -> CommandResult<()> {
+        if !(2..=25).contains(&size) {
+            return Err("unacceptable size".to_string());
+        }
+
+        self.size = size as u8;
+        self.moves.clear();
+        Ok(())
+    }
+
+    fn clear_board(&mut self) -> CommandResult<()> {
+        self.moves.clear();
+        Ok(())
+    }
+
+    fn komi(&mut self, _komi: f32) -> CommandResult<()> {
+        Ok(())
+    }
+
+    fn play(&mut self, mv: Move) -> CommandResult<()> {
+        let on_board = match mv.vertex {
+            Vertex::Point { column, row } => column < self.size && row < self.size,
+            Vertex::Pass => true,
+            Vertex::Resign => false,
+        };
+
+        if !on_board || self.is_occupied(mv.vertex) {
+            return Err("illegal move".to_string());
+        }
+
+        self.moves.push(mv);
+        Ok(())
+    }
+
+    fn genmove(&mut self, color: Color) -> CommandResult<Vertex> {
+        let vertex = self.first_empty();
+        self.moves.push(Move::new(color, vertex));
+        Ok(vertex)
+    }
+
+    fn optional_commands(&self) -> Vec<String> {
+        vec!["undo".to_string(), "reg_genmove".to_string()]
+    }
+
+    fn reg_genmove(&mut self, _color: Color) -> CommandResult<Vertex> {
+        Ok(self.first_empty())
+    }
+
+    fn undo(&mut self) -> CommandResult<()> {
+        self.moves.pop().map(|_| ()).ok_or_else(|| "cannot undo".to_string())
+    }
+}
+
+fn main() {
+    let mut engine = FakeEngine {
+        size: 19,
+        moves: Vec::new(),
+    };
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    if let Err(e) = run_session(stdin.lock(), stdout.lock(), &mut engine) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,366 @@
+//! Custom derive for the `GtpCommand` trait from the `go-text-protocol`
+//! crate.
+//!
+//! You probably don't want to use this crate directly, the derive is
+//! re-exported by `go-text-protocol` itself.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, LitStr, Variant};
+
+/// Derive `GtpCommand`, `TryFrom<RawCommand>`, and `Display` for an enum.
+///
+/// See the `go_text_protocol::GtpCommand` docs for the attributes which are
+/// understood.
+#[proc_macro_derive(GtpCommand, attributes(gtp))]
+pub fn derive_gtp_command(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// How a single field gets populated from a `RawCommand`.
+enum FieldKind {
+    /// The command's id.
+    Id,
+    /// A single argument.
+    Arg,
+    /// All remaining arguments.
+    Rest,
+}
+
+struct CommandField {
+    /// The field's name, or `None` for tuple fields.
+    ident: Option<syn::Ident>,
+    /// The name to bind the field to when destructuring.
+    binding: syn::Ident,
+    ty: syn::Type,
+    kind: FieldKind,
+}
+
+struct Command {
+    variant: syn::Ident,
+    wire_name: String,
+    is_other: bool,
+    named_fields: bool,
+    fields: Vec<CommandField>,
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let variants = match input.data {
+        Data::Enum(ref data) => &data.variants,
+        _ => {
+            return Err(Error::new(input.span(), "GtpCommand can only be derived for enums"));
+        }
+    };
+
+    let commands = variants.iter().map(analyse_variant).collect::<Result<Vec<_>, _>>()?;
+
+    let others: Vec<_> = commands.iter().filter(|c| c.is_other).collect();
+    if others.len() > 1 {
+        return Err(Error::new(input.span(), "Only one variant can be #[gtp(other)]"));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let wire_names: Vec<_> = commands.iter()
+        .filter(|c| !c.is_other)
+        .map(|c| &c.wire_name)
+        .collect();
+
+    let conversions = commands.iter().filter(|c| !c.is_other).map(|c| conversion(name, c));
+
+    let fallback = match others.first() {
+        Some(other) => {
+            let variant = &other.variant;
+            quote!(Ok(#name::#variant(raw)))
+        }
+        None => {
+            quote! {
+                Err(::go_text_protocol::ErrorKind::UnknownCommand(raw.name).into())
+            }
+        }
+    };
+
+    let display_arms = commands.iter().map(|c| display_arm(name, c));
+
+    Ok(quote! {
+        impl #impl_generics ::go_text_protocol::GtpCommand for #name #ty_generics #where_clause {
+            const COMMAND_NAMES: &'static [&'static str] = &[#(#wire_names),*];
+        }
+
+        impl #impl_generics ::std::convert::TryFrom<::go_text_protocol::RawCommand>
+            for #name #ty_generics #where_clause
+        {
+            type Error = ::go_text_protocol::Error;
+
+            fn try_from(raw: ::go_text_protocol::RawCommand)
+                -> ::go_text_protocol::Result<Self>
+            {
+                #(#conversions)*
+
+                #fallback
+            }
+        }
+
+        impl #impl_generics ::std::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                match *self {
+                    #(#display_arms)*
+                }
+            }
+        }
+    })
+}
+
+fn analyse_variant(variant: &Variant) -> Result<Command, Error> {
+    let mut wire_name = None;
+    let mut is_other = false;
+
+    for attr in gtp_attributes(&variant.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                let value: LitStr = meta.value()?.parse()?;
+                wire_name = Some(value.value());
+                Ok(())
+            } else if meta.path.is_ident("other") {
+                is_other = true;
+                Ok(())
+            } else {
+                Err(meta.error("Expected `name = \"...\"` or `other`"))
+            }
+        })?;
+    }
+
+    let named_fields = matches!(variant.fields, Fields::Named(_));
+
+    if is_other {
+        if variant.fields.len() != 1 || named_fields {
+            return Err(Error::new(variant.span(),
+                                  "A #[gtp(other)] variant must have a single RawCommand field"));
+        }
+
+        return Ok(Command {
+            variant: variant.ident.clone(),
+            wire_name: String::new(),
+            is_other,
+            named_fields,
+            fields: Vec::new(),
+        });
+    }
+
+    let mut fields = Vec::new();
+
+    for (i, field) in variant.fields.iter().enumerate() {
+        let mut kind = FieldKind::Arg;
+
+        for attr in gtp_attributes(&field.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("id") {
+                    kind = FieldKind::Id;
+                    Ok(())
+                } else if meta.path.is_ident("rest") {
+                    kind = FieldKind::Rest;
+                    Ok(())
+                } else {
+                    Err(meta.error("Expected `id` or `rest`"))
+                }
+            })?;
+        }
+
+        fields.push(CommandField {
+            ident: field.ident.clone(),
+            binding: format_ident!("field_{}", i),
+            ty: field.ty.clone(),
+            kind,
+        });
+    }
+
+    let rest_fields = fields.iter().filter(|f| matches!(f.kind, FieldKind::Rest)).count();
+    let last_is_rest = fields.last().map(|f| matches!(f.kind, FieldKind::Rest)).unwrap_or(false);
+
+    if rest_fields > 1 || (rest_fields == 1 && !last_is_rest) {
+        return Err(Error::new(variant.span(),
+                              "Only the last field can be marked with #[gtp(rest)]"));
+    }
+
+    let wire_name = wire_name.unwrap_or_else(|| snake_case(&variant.ident.to_string()));
+
+    Ok(Command {
+        variant: variant.ident.clone(),
+        wire_name,
+        is_other,
+        named_fields,
+        fields,
+    })
+}
+
+fn gtp_attributes(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("gtp"))
+}
+
+/// Generate the code which checks whether a `RawCommand` is this command and
+/// converts it if so.
+fn conversion(name: &syn::Ident, command: &Command) -> TokenStream2 {
+    let variant = &command.variant;
+    let wire_name = &command.wire_name;
+
+    let num_args = command.fields.iter().filter(|f| matches!(f.kind, FieldKind::Arg)).count();
+    let has_rest = command.fields.iter().any(|f| matches!(f.kind, FieldKind::Rest));
+
+    let max_args = if has_rest {
+        quote!(usize::MAX)
+    } else {
+        quote!(#num_args)
+    };
+
+    let mut index = 0_usize;
+    let values: Vec<_> = command.fields
+        .iter()
+        .map(|field| {
+            let ty = &field.ty;
+            let value = match field.kind {
+                FieldKind::Id => quote!(raw.count),
+                FieldKind::Arg => {
+                    let value = quote!(raw.arg::<#ty>(#index)?);
+                    index += 1;
+                    value
+                }
+                FieldKind::Rest => {
+                    quote! {
+                        (#index..raw.args.len())
+                            .map(|i| raw.arg(i))
+                            .collect::<::go_text_protocol::Result<#ty>>()?
+                    }
+                }
+            };
+
+            match field.ident {
+                Some(ref ident) => quote!(#ident: #value),
+                None => value,
+            }
+        })
+        .collect();
+
+    let construct = if command.fields.is_empty() {
+        quote!(#name::#variant)
+    } else if command.named_fields {
+        quote!(#name::#variant { #(#values),* })
+    } else {
+        quote!(#name::#variant( #(#values),* ))
+    };
+
+    quote! {
+        if raw.name.eq_ignore_ascii_case(#wire_name) {
+            raw.check_arg_count(#num_args, #max_args)?;
+            return Ok(#construct);
+        }
+    }
+}
+
+/// Generate the match arm used to write a command back out in its wire
+/// format.
+fn display_arm(name: &syn::Ident, command: &Command) -> TokenStream2 {
+    let variant = &command.variant;
+
+    if command.is_other {
+        return quote! {
+            #name::#variant(ref raw) => ::std::fmt::Display::fmt(raw, f),
+        };
+    }
+
+    let wire_name = &command.wire_name;
+    let bindings: Vec<_> = command.fields
+        .iter()
+        .map(|field| {
+            let binding = &field.binding;
+            match field.ident {
+                Some(ref ident) => quote!(#ident: ref #binding),
+                None => quote!(ref #binding),
+            }
+        })
+        .collect();
+
+    let pattern = if command.fields.is_empty() {
+        quote!(#name::#variant)
+    } else if command.named_fields {
+        quote!(#name::#variant { #(#bindings),* })
+    } else {
+        quote!(#name::#variant( #(#bindings),* ))
+    };
+
+    let id = command.fields.iter().find(|f| matches!(f.kind, FieldKind::Id)).map(|f| {
+        let binding = &f.binding;
+        quote! {
+            if let Some(id) = *#binding {
+                write!(f, "{} ", id)?;
+            }
+        }
+    });
+
+    let args = command.fields.iter().map(|field| {
+        let binding = &field.binding;
+        match field.kind {
+            FieldKind::Id => quote!(),
+            FieldKind::Arg => quote!(write!(f, " {}", #binding)?;),
+            FieldKind::Rest => {
+                quote! {
+                    for arg in #binding.iter() {
+                        write!(f, " {}", arg)?;
+                    }
+                }
+            }
+        }
+    });
+
+    quote! {
+        #pattern => {
+            #id
+            write!(f, "{}", #wire_name)?;
+            #(#args)*
+            Ok(())
+        }
+    }
+}
+
+/// Convert a `CamelCase` variant name into its `snake_case` equivalent.
+fn snake_case(name: &str) -> String {
+    let mut buffer = String::new();
+
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                buffer.push('_');
+            }
+            buffer.extend(c.to_lowercase());
+        } else {
+            buffer.push(c);
+        }
+    }
+
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_to_snake_case() {
+        let inputs = vec![("Quit", "quit"),
+                          ("ListCommands", "list_commands"),
+                          ("FinalStatusList", "final_status_list"),
+                          ("ABC", "a_b_c")];
+
+        for (src, should_be) in inputs {
+            assert_eq!(snake_case(src), should_be);
+        }
+    }
+}
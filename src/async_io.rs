@@ -0,0 +1,663 @@
+//! Async versions of the engine session loop and controller, built on
+//! `tokio`.
+//!
+//! This module is only available when the `tokio` feature is enabled.
+//!
+//! The core of it is a pair of codecs which can be used with
+//! `tokio_util::codec::{FramedRead, FramedWrite}`:
+//!
+//! - `CommandCodec` is for the engine side, it decodes commands (applying the
+//!   usual preprocessing) and encodes responses
+//! - `ResponseCodec` is for the controller side, it decodes responses and
+//!   encodes commands
+//!
+//! On top of those are `run_session_async()` and `AsyncController`, which
+//! mirror `run_session()` and `Controller`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use futures::StreamExt;
+//! use tokio_util::codec::FramedRead;
+//! use go_text_protocol::async_io::ResponseCodec;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let stream: &[u8] = b"=1 D4\n\n?2 illegal move\n\n";
+//! let mut responses = FramedRead::new(stream, ResponseCodec::default());
+//!
+//! let first = responses.next().await.unwrap().unwrap();
+//! assert_eq!(first.payload, "D4");
+//!
+//! let second = responses.next().await.unwrap().unwrap();
+//! assert!(!second.is_success());
+//! # });
+//! ```
+
+use std::fmt::{Display, Write as FmtWrite};
+use std::process::Stdio;
+use std::str;
+
+use bytes::{Buf, BytesMut};
+use futures::StreamExt;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio_util::codec::{Decoder, Encoder, FramedRead};
+
+use crate::commands::StandardCommand;
+use crate::engine::{Engine, SessionOptions, respond_to_line};
+use crate::errors::*;
+use crate::parser::{Parser, RawCommand};
+use crate::response::Response;
+use crate::values::{Boolean, Color, Int, Move, StoneStatus, Vertex};
+
+/// A codec for the engine side of a session, decoding commands and encoding
+/// responses.
+///
+/// Blank lines and comments are skipped. The decoded item is itself a
+/// `Result` so a line which can't be parsed doesn't end the stream, the outer
+/// error is reserved for I/O errors.
+///
+/// By default lines can be any length, so a misbehaving controller could
+/// make the codec buffer an unbounded amount of input. Use
+/// `CommandCodec::new_with_max_length()` to put a limit on it.
+#[derive(Clone, Debug)]
+pub struct CommandCodec {
+    max_length: usize,
+    discarding: bool,
+}
+
+impl CommandCodec {
+    /// Create a codec which accepts lines of any length.
+    pub fn new() -> CommandCodec {
+        CommandCodec::new_with_max_length(usize::MAX)
+    }
+
+    /// Create a codec which rejects lines longer than `max_length` bytes
+    /// (not counting the newline).
+    ///
+    /// An overlong line is decoded as an `ErrorKind::LineTooLong` error and
+    /// the rest of it is discarded, so the stream carries on with the next
+    /// line.
+    pub fn new_with_max_length(max_length: usize) -> CommandCodec {
+        CommandCodec {
+            max_length,
+            discarding: false,
+        }
+    }
+
+    /// The longest line this codec will accept.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl Default for CommandCodec {
+    fn default() -> CommandCodec {
+        CommandCodec::new()
+    }
+}
+
+impl CommandCodec {
+    /// Find the next line which isn't blank or a comment, returning it along
+    /// with the command it contains.
+    fn next_command(&mut self, src: &mut BytesMut, eof: bool)
+                    -> Option<(BytesMut, Result<RawCommand>)> {
+        loop {
+            if self.discarding {
+                match src.iter().position(|&b| b == b'\n') {
+                    Some(newline) => {
+                        src.advance(newline + 1);
+                        self.discarding = false;
+                    }
+                    None => {
+                        src.clear();
+                        return None;
+                    }
+                }
+            }
+
+            // no need to look any further than the longest line we accept
+            let limit = src.len().min(self.max_length.saturating_add(1));
+
+            let line = match src[..limit].iter().position(|&b| b == b'\n') {
+                Some(newline) => src.split_to(newline + 1),
+                None if src.len() > self.max_length => {
+                    // keep the start of the line so its id can still be
+                    // echoed, the rest of it (which may not have arrived
+                    // yet) gets thrown away
+                    self.discarding = true;
+                    let err = ErrorKind::LineTooLong(self.max_length).into();
+                    return Some((src.split_to(self.max_length), Err(err)));
+                }
+                // the last line might not have a trailing newline
+                None if eof && !src.is_empty() => src.split(),
+                None => return None,
+            };
+
+            if let Some(cmd) = parse_line(&line) {
+                return Some((line, cmd));
+            }
+        }
+    }
+}
+
+impl Decoder for CommandCodec {
+    type Item = Result<RawCommand>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Result<RawCommand>>> {
+        Ok(self.next_command(src, false).map(|(_, cmd)| cmd))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Result<RawCommand>>> {
+        Ok(self.next_command(src, true).map(|(_, cmd)| cmd))
+    }
+}
+
+/// Decodes the same commands as `CommandCodec`, but keeps each line around
+/// so the session can echo its id exactly as it was written.
+#[derive(Clone, Debug, Default)]
+struct SessionCodec {
+    commands: CommandCodec,
+}
+
+impl Decoder for SessionCodec {
+    type Item = (String, Result<RawCommand>);
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        Ok(self.commands.next_command(src, false).map(lossy))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        Ok(self.commands.next_command(src, true).map(lossy))
+    }
+}
+
+fn lossy((line, cmd): (BytesMut, Result<RawCommand>)) -> (String, Result<RawCommand>) {
+    (String::from_utf8_lossy(&line).into_owned(), cmd)
+}
+
+/// Parse a single line, returning `None` if it should be ignored.
+fn parse_line(line: &[u8]) -> Option<Result<RawCommand>> {
+    // invalid UTF-8 is replaced, the same as `CommandReader`
+    let line = String::from_utf8_lossy(line);
+
+    match Parser::new(&line).parse() {
+        Err(ref e) if *e.kind() == ErrorKind::EmptyLine => None,
+        other => Some(other),
+    }
+}
+
+impl Encoder<Response> for CommandCodec {
+    type Error = Error;
+
+    fn encode(&mut self, response: Response, dst: &mut BytesMut) -> Result<()> {
+        write!(dst, "{}", response).chain_err(|| "Unable to format the response")
+    }
+}
+
+/// A codec for the controller side of a session, decoding responses and
+/// encoding commands.
+#[derive(Clone, Debug, Default)]
+pub struct ResponseCodec {
+    /// Where to start looking for the next line, so a response arriving in
+    /// pieces isn't scanned from the beginning every time.
+    next_index: usize,
+    /// Whether any of the lines before `next_index` had something on them.
+    seen_content: bool,
+}
+
+impl Decoder for ResponseCodec {
+    type Item = Response;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Response>> {
+        while let Some(newline) = src[self.next_index..].iter().position(|&b| b == b'\n') {
+            let start = self.next_index;
+            let end = start + newline + 1;
+            let is_blank = src[start..end].iter().all(|&b| b == b'\r' || b == b'\n');
+
+            if is_blank && self.seen_content {
+                self.next_index = 0;
+                self.seen_content = false;
+
+                let response = src.split_to(end);
+                let response = str::from_utf8(&response)
+                    .chain_err(|| "The response isn't valid UTF-8")?;
+                return response.parse().map(Some);
+            }
+
+            self.seen_content |= !is_blank;
+            self.next_index = end;
+        }
+
+        Ok(None)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Response>> {
+        match self.decode(src)? {
+            Some(response) => Ok(Some(response)),
+            None if src.iter().all(|b| b.is_ascii_whitespace()) => Ok(None),
+            None => Err(ErrorKind::IncompleteResponse.into()),
+        }
+    }
+}
+
+impl Encoder<RawCommand> for ResponseCodec {
+    type Error = Error;
+
+    fn encode(&mut self, command: RawCommand, dst: &mut BytesMut) -> Result<()> {
+        writeln!(dst, "{}", command).chain_err(|| "Unable to format the command")
+    }
+}
+
+/// Run a GTP session asynchronously, reading commands from `reader` and
+/// writing responses to `writer` until the controller sends `quit` or closes
+/// the stream.
+///
+/// This is the async equivalent of `run_session()`.
+pub async fn run_session_async<R, W, E>(reader: R, writer: W, engine: &mut E) -> Result<()>
+    where R: AsyncRead + Unpin,
+          W: AsyncWrite + Unpin,
+          E: Engine + ?Sized
+{
+    run_session_async_with(reader, writer, engine, SessionOptions::default()).await
+}
+
+/// Run a GTP session asynchronously, the same as `run_session_async()`, but
+/// with some extra options.
+///
+/// This is the async equivalent of `run_session_with()`, and replies to
+/// every line with exactly the same bytes.
+pub async fn run_session_async_with<R, W, E>(reader: R,
+                                             mut writer: W,
+                                             engine: &mut E,
+                                             options: SessionOptions)
+                                             -> Result<()>
+    where R: AsyncRead + Unpin,
+          W: AsyncWrite + Unpin,
+          E: Engine + ?Sized
+{
+    let mut lines = FramedRead::new(reader, SessionCodec::default());
+
+    while let Some(item) = lines.next().await {
+        let (line, cmd) = item?;
+        let (response, quit) = respond_to_line(&line, cmd, engine, options);
+
+        writer.write_all(response.as_bytes()).await?;
+        writer.flush().await?;
+
+        if quit {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// An async version of `Controller`.
+pub struct AsyncController<W, R> {
+    writer: W,
+    reader: FramedRead<R, ResponseCodec>,
+    next_id: u32,
+    child: Option<Child>,
+}
+
+/// An `AsyncController` talking to an engine subprocess.
+pub type AsyncProcessController = AsyncController<ChildStdin, ChildStdout>;
+
+impl AsyncProcessController {
+    /// Start an engine as a subprocess and talk to it over its `stdin` and
+    /// `stdout`.
+    ///
+    /// The engine will be killed if the controller is dropped without calling
+    /// `quit()`.
+    pub fn spawn(command: &mut Command) -> Result<AsyncProcessController> {
+        let mut child = command.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .chain_err(|| "Unable to start the engine")?;
+
+        let stdin = child.stdin.take().expect("stdin is always piped");
+        let stdout = child.stdout.take().expect("stdout is always piped");
+
+        let mut controller = AsyncController::new(stdin, stdout);
+        controller.child = Some(child);
+
+        Ok(controller)
+    }
+}
+
+impl<W, R> AsyncController<W, R>
+    where W: AsyncWrite + Unpin,
+          R: AsyncRead + Unpin
+{
+    /// Create a controller which talks to an engine over the provided
+    /// streams.
+    pub fn new(writer: W, reader: R) -> AsyncController<W, R> {
+        AsyncController {
+            writer,
+            reader: FramedRead::new(reader, ResponseCodec::default()),
+            next_id: 1,
+            child: None,
+        }
+    }
+
+    /// Send a command to the engine and wait for its response.
+    ///
+    /// The command is given a fresh id, and it's an error if the engine
+    /// replies with a different one. Note that failure responses (`?`) are
+    /// *not* turned into an error here.
+    pub async fn send<C: Display>(&mut self, command: C) -> Result<Response> {
+        // ids only need to be unique among outstanding commands, so start
+        // again from 1 rather than overflowing
+        let id = self.next_id;
+        self.next_id = id.checked_add(1).unwrap_or(1);
+
+        let line = format!("{} {}\n", id, command);
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.flush().await?;
+
+        let response = match self.reader.next().await {
+            Some(response) => response?,
+            None => bail!(ErrorKind::IncompleteResponse),
+        };
+
+        if response.id != Some(id) {
+            bail!(ErrorKind::MismatchedResponse(id, response.id));
+        }
+
+        Ok(response)
+    }
+
+    /// Send a command, turning a failure response into an error.
+    pub async fn execute<C: Display>(&mut self, command: C) -> Result<String> {
+        self.send(command).await?.into_result()
+    }
+
+    /// Ask which version of the protocol the engine speaks.
+    pub async fn protocol_version(&mut self) -> Result<u32> {
+        self.send(StandardCommand::ProtocolVersion).await?.value::<Int>().map(Into::into)
+    }
+
+    /// Get the engine's name.
+    pub async fn name(&mut self) -> Result<String> {
+        self.execute(StandardCommand::Name).await
+    }
+
+    /// Get the engine's version.
+    pub async fn version(&mut self) -> Result<String> {
+        self.execute(StandardCommand::Version).await
+    }
+
+    /// Ask whether the engine knows a particular command.
+    pub async fn known_command(&mut self, name: &str) -> Result<bool> {
+        self.send(StandardCommand::KnownCommand(name.to_string()))
+            .await?
+            .value::<Boolean>()
+            .map(Into::into)
+    }
+
+    /// Get the names of every command the engine knows.
+    pub async fn list_commands(&mut self) -> Result<Vec<String>> {
+        let response = self.execute(StandardCommand::ListCommands).await?;
+        Ok(response.lines().map(|line| line.trim().to_string()).collect())
+    }
+
+    /// Change the board size.
+    pub async fn boardsize(&mut self, size: u32) -> Result<()> {
+        self.execute(StandardCommand::BoardSize(size)).await.map(|_| ())
+    }
+
+    /// Clear the board.
+    pub async fn clear_board(&mut self) -> Result<()> {
+        self.execute(StandardCommand::ClearBoard).await.map(|_| ())
+    }
+
+    /// Set the komi.
+    pub async fn komi(&mut self, komi: f32) -> Result<()> {
+        self.execute(StandardCommand::Komi(komi)).await.map(|_| ())
+    }
+
+    /// Tell the engine about a move.
+    pub async fn play(&mut self, mv: Move) -> Result<()> {
+        self.execute(StandardCommand::Play(mv)).await.map(|_| ())
+    }
+
+    /// Ask the engine to generate (and play) a move.
+    pub async fn genmove(&mut self, color: Color) -> Result<Move> {
+        let vertex = self.send(StandardCommand::GenMove(color)).await?.value::<Vertex>()?;
+        Ok(Move::new(color, vertex))
+    }
+
+    /// Ask the engine which move it would play, without playing it.
+    pub async fn reg_genmove(&mut self, color: Color) -> Result<Move> {
+        let vertex = self.send(StandardCommand::RegGenMove(color)).await?.value::<Vertex>()?;
+        Ok(Move::new(color, vertex))
+    }
+
+    /// Take back the last move.
+    pub async fn undo(&mut self) -> Result<()> {
+        self.execute(StandardCommand::Undo).await.map(|_| ())
+    }
+
+    /// Place fixed handicap stones, returning where they went.
+    pub async fn fixed_handicap(&mut self, number_of_stones: u32) -> Result<Vec<Vertex>> {
+        self.send(StandardCommand::FixedHandicap(number_of_stones)).await?.values()
+    }
+
+    /// Let the engine choose where to put its handicap stones, returning
+    /// where they went.
+    pub async fn place_free_handicap(&mut self, number_of_stones: u32) -> Result<Vec<Vertex>> {
+        self.send(StandardCommand::PlaceFreeHandicap(number_of_stones)).await?.values()
+    }
+
+    /// Tell the engine where the handicap stones are.
+    pub async fn set_free_handicap(&mut self, vertices: &[Vertex]) -> Result<()> {
+        self.execute(StandardCommand::SetFreeHandicap(vertices.to_vec())).await.map(|_| ())
+    }
+
+    /// Set up the time control (see `time::TimeControl` for how the
+    /// arguments are interpreted).
+    pub async fn time_settings(&mut self,
+                               main_time: u32,
+                               byo_yomi_time: u32,
+                               byo_yomi_stones: u32)
+                               -> Result<()> {
+        let command = StandardCommand::TimeSettings {
+            main_time,
+            byo_yomi_time,
+            byo_yomi_stones,
+        };
+        self.execute(command).await.map(|_| ())
+    }
+
+    /// Tell the engine how much time a player has left.
+    pub async fn time_left(&mut self, color: Color, time: u32, stones: u32) -> Result<()> {
+        self.execute(StandardCommand::TimeLeft { color, time, stones }).await.map(|_| ())
+    }
+
+    /// Load a position from an SGF file, returning the colour to play next.
+    pub async fn loadsgf(&mut self, filename: &str, move_number: Option<u32>) -> Result<Color> {
+        let command = StandardCommand::LoadSgf {
+            filename: filename.to_string(),
+            move_number,
+        };
+        self.send(command).await?.value()
+    }
+
+    /// Ask the engine to score the game.
+    pub async fn final_score(&mut self) -> Result<String> {
+        self.execute(StandardCommand::FinalScore).await
+    }
+
+    /// Ask the engine which stones have a particular status at the end of
+    /// the game.
+    pub async fn final_status_list(&mut self, status: StoneStatus) -> Result<Vec<Vertex>> {
+        self.send(StandardCommand::FinalStatusList(status)).await?.values()
+    }
+
+    /// Ask the engine to draw the board.
+    pub async fn showboard(&mut self) -> Result<String> {
+        self.execute(StandardCommand::ShowBoard).await
+    }
+
+    /// End the session, waiting for the engine to exit if it's a
+    /// subprocess.
+    pub async fn quit(mut self) -> Result<()> {
+        self.execute(StandardCommand::Quit).await?;
+
+        if let Some(mut child) = self.child.take() {
+            child.wait().await.chain_err(|| "Unable to wait for the engine to exit")?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all<D: Decoder<Error = Error>>(mut codec: D, src: &str) -> Vec<D::Item> {
+        let mut buffer = BytesMut::from(src);
+        let mut items = Vec::new();
+
+        while let Some(item) = codec.decode_eof(&mut buffer).unwrap() {
+            items.push(item);
+        }
+
+        items
+    }
+
+    #[test]
+    fn decode_commands() {
+        let src = "1 name\n\n# comment\r\nplay b\tD4\r\nbad\x01\n3 quit";
+
+        let got: Vec<_> = decode_all(CommandCodec::default(), src)
+            .into_iter()
+            .map(|item| item.unwrap().to_string())
+            .collect();
+
+        assert_eq!(got, vec!["1 name", "play b D4", "bad", "3 quit"]);
+    }
+
+    #[test]
+    fn unparseable_commands_dont_end_the_stream() {
+        let got = decode_all(CommandCodec::default(), "5\nname\n");
+
+        assert_eq!(got.len(), 2);
+        assert!(got[0].is_err());
+        assert_eq!(got[1].as_ref().unwrap().name, "name");
+    }
+
+    #[test]
+    fn overlong_lines_are_rejected() {
+        let src = "1 name\n2 play black D4 # a long comment\n3 quit\n";
+
+        let got = decode_all(CommandCodec::new_with_max_length(10), src);
+
+        assert_eq!(got.len(), 3);
+        assert_eq!(got[0].as_ref().unwrap().name, "name");
+        assert_eq!(*got[1].as_ref().unwrap_err().kind(), ErrorKind::LineTooLong(10));
+        assert_eq!(got[2].as_ref().unwrap().name, "quit");
+    }
+
+    #[test]
+    fn overlong_lines_are_discarded_as_they_arrive() {
+        let mut codec = CommandCodec::new_with_max_length(10);
+        let mut buffer = BytesMut::from("1 genmove black");
+
+        assert!(codec.decode(&mut buffer).unwrap().unwrap().is_err());
+
+        buffer.extend_from_slice(b" and then some more");
+        assert!(codec.decode(&mut buffer).unwrap().is_none());
+        assert!(buffer.is_empty());
+
+        buffer.extend_from_slice(b"\n2 name\n");
+        let got = codec.decode(&mut buffer).unwrap().unwrap().unwrap();
+        assert_eq!(got.to_string(), "2 name");
+    }
+
+    #[test]
+    fn overlong_lines_keep_their_id() {
+        let mut codec = SessionCodec { commands: CommandCodec::new_with_max_length(10) };
+        let mut buffer = BytesMut::from("12 play black D4 # a long comment\n");
+
+        let (line, cmd) = codec.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(line, "12 play bl");
+        assert_eq!(*cmd.unwrap_err().kind(), ErrorKind::LineTooLong(10));
+    }
+
+    #[test]
+    fn partial_commands_wait_for_more_input() {
+        let mut codec = CommandCodec::default();
+        let mut buffer = BytesMut::from("1 gen");
+
+        assert!(codec.decode(&mut buffer).unwrap().is_none());
+
+        buffer.extend_from_slice(b"move black\n");
+        let got = codec.decode(&mut buffer).unwrap().unwrap().unwrap();
+        assert_eq!(got.to_string(), "1 genmove black");
+    }
+
+    #[test]
+    fn decode_responses() {
+        let src = "\n=1 D4\n\n?2 cannot undo\r\n\r\n= a\nb\n\n";
+
+        let got = decode_all(ResponseCodec::default(), src);
+
+        assert_eq!(got,
+                   vec![Response::success(Some(1), "D4"),
+                        Response::failure(Some(2), "cannot undo"),
+                        Response::success(None, "a\nb")]);
+    }
+
+    #[test]
+    fn partial_responses() {
+        let mut codec = ResponseCodec::default();
+        let mut buffer = BytesMut::from("=1 a\n");
+
+        assert!(codec.decode(&mut buffer).unwrap().is_none());
+        assert!(codec.decode_eof(&mut buffer).is_err());
+
+        buffer.extend_from_slice(b"\n");
+        let got = codec.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(got, Response::success(Some(1), "a"));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn responses_arriving_a_byte_at_a_time() {
+        let src = b"\n=1 a\nb\n\n?2 c\n\n";
+        let mut codec = ResponseCodec::default();
+        let mut buffer = BytesMut::new();
+        let mut got = Vec::new();
+
+        for &byte in src {
+            buffer.extend_from_slice(&[byte]);
+            got.extend(codec.decode(&mut buffer).unwrap());
+        }
+
+        assert_eq!(got,
+                   vec![Response::success(Some(1), "a\nb"),
+                        Response::failure(Some(2), "c")]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn encode_commands_and_responses() {
+        let mut buffer = BytesMut::new();
+
+        CommandCodec::default()
+            .encode(Response::failure(Some(3), "illegal move"), &mut buffer)
+            .unwrap();
+        let cmd: RawCommand = crate::parser::parse("4 genmove w").unwrap();
+        ResponseCodec::default().encode(cmd, &mut buffer).unwrap();
+
+        assert_eq!(&buffer[..], &b"?3 illegal move\n\n4 genmove w\n"[..]);
+    }
+}
@@ -0,0 +1,104 @@
+//! Run GNU Go style regression files against a GTP engine.
+//!
+//! ```text
+//! gtp-regress FILE.tst... -- ENGINE [ARGS...]
+//! ```
+//!
+//! A fresh copy of the engine is started for each file, in the file's
+//! directory so any `loadsgf` paths are relative to it (a relative `ENGINE`
+//! path is still relative to the current directory). Failures and
+//! unexpected passes are printed along with a summary, and the exit code is
+//! non-zero if there were any.
+
+extern crate go_text_protocol;
+
+use std::env;
+use std::error::Error as StdError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use go_text_protocol::controller::Controller;
+use go_text_protocol::regression::{self, Summary};
+use go_text_protocol::Result;
+
+fn run_file(path: &Path, engine: &[String]) -> Result<Summary> {
+    let src = fs::read_to_string(path)?;
+    let steps = regression::parse_tests(&src)?;
+
+    let mut command = Command::new(engine_program(&engine[0])?);
+    command.args(&engine[1..]);
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        command.current_dir(dir);
+    }
+
+    let mut controller = Controller::spawn(&mut command)?;
+    let summary = regression::run_tests(&mut controller, &steps)?;
+    controller.quit()?;
+
+    Ok(summary)
+}
+
+/// Engines given as a path are found relative to the directory we were run
+/// from, not the one the engine gets started in.
+fn engine_program(program: &str) -> Result<PathBuf> {
+    let program = Path::new(program);
+
+    if program.components().count() > 1 {
+        Ok(env::current_dir()?.join(program))
+    } else {
+        Ok(program.to_path_buf())
+    }
+}
+
+fn print_error(path: &str, error: &dyn StdError) {
+    eprint!("{}: Error: {}", path, error);
+
+    let mut source = error.source();
+    while let Some(cause) = source {
+        eprint!(": {}", cause);
+        source = cause.source();
+    }
+
+    eprintln!();
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let (files, engine) = match args.iter().position(|arg| arg == "--") {
+        Some(ix) if ix > 0 && ix + 1 < args.len() => (&args[..ix], &args[ix + 1..]),
+        _ => {
+            eprintln!("Usage: gtp-regress FILE.tst... -- ENGINE [ARGS...]");
+            process::exit(2);
+        }
+    };
+
+    let mut total = Summary::default();
+    let mut errors = 0;
+
+    for file in files {
+        match run_file(Path::new(file), engine) {
+            Ok(summary) => {
+                for result in summary.surprises() {
+                    println!("{}:{}: {}", file, result.line_number, result);
+                }
+                println!("{}: {}", file, summary);
+
+                total.results.extend(summary.results);
+            }
+            Err(e) => {
+                print_error(file, &e);
+                errors += 1;
+            }
+        }
+    }
+
+    if files.len() > 1 {
+        println!("Total: {}", total);
+    }
+
+    if errors > 0 || !total.is_success() {
+        process::exit(1);
+    }
+}
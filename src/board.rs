@@ -0,0 +1,699 @@
+//! A Go board which knows the rules for placing stones.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use go_text_protocol::{Color, Move};
+//! use go_text_protocol::board::Board;
+//!
+//! let mut board = Board::new(9).unwrap();
+//!
+//! board.play(Move::new(Color::Black, "E5".parse().unwrap())).unwrap();
+//! assert_eq!(board.get("E5".parse().unwrap()), Some(Color::Black));
+//!
+//! // you can't play on top of another stone
+//! let err = board.play(Move::new(Color::White, "E5".parse().unwrap())).unwrap_err();
+//! assert_eq!(err, "illegal move");
+//! ```
+//!
+//! Printing a `Board` draws it the same way GNU Go's `showboard` does.
+//!
+//! ```rust
+//! # use go_text_protocol::{Color, Move};
+//! # use go_text_protocol::board::Board;
+//! let mut board = Board::new(5).unwrap();
+//! board.play(Move::new(Color::Black, "B2".parse().unwrap())).unwrap();
+//!
+//! let diagram = "   A B C D E
+//!  5 . . . . . 5
+//!  4 . . . . . 4
+//!  3 . . + . . 3
+//!  2 .(X). . . 2     WHITE (O) has captured 0 stones
+//!  1 . . . . . 1     BLACK (X) has captured 0 stones
+//!    A B C D E";
+//! assert_eq!(board.to_string(), diagram);
+//! ```
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::engine::{BAD_VERTEX_LIST, BOARD_NOT_EMPTY, CommandResult, ILLEGAL_MOVE,
+                    INVALID_NUMBER_OF_STONES, UNACCEPTABLE_SIZE};
+use crate::handicap;
+use crate::values::{Color, Move, Vertex, COLUMN_LETTERS, MAX_BOARD_SIZE};
+
+/// The smallest board size we support.
+pub const MIN_BOARD_SIZE: u8 = 2;
+
+/// The state of a Go board.
+///
+/// Suicide isn't allowed, and simple ko (immediately retaking a single
+/// stone) is detected, but there's no check for superko.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Board {
+    size: u8,
+    points: Vec<Option<Color>>,
+    /// The point which can't be played on because of ko, and the colour
+    /// which isn't allowed to play there.
+    ko: Option<(usize, Color)>,
+    last_move: Option<Move>,
+    black_captures: u32,
+    white_captures: u32,
+}
+
+impl Board {
+    /// Create an empty board, failing with `unacceptable size` if `size`
+    /// isn't between `MIN_BOARD_SIZE` and `MAX_BOARD_SIZE`.
+    pub fn new(size: u32) -> CommandResult<Board> {
+        if size < u32::from(MIN_BOARD_SIZE) || size > u32::from(MAX_BOARD_SIZE) {
+            return Err(UNACCEPTABLE_SIZE.to_string());
+        }
+
+        let size = size as u8;
+
+        Ok(Board {
+            size,
+            points: vec![None; usize::from(size) * usize::from(size)],
+            ko: None,
+            last_move: None,
+            black_captures: 0,
+            white_captures: 0,
+        })
+    }
+
+    /// The number of rows (and columns) on the board.
+    pub fn size(&self) -> u32 {
+        u32::from(self.size)
+    }
+
+    /// Remove every stone from the board, forgetting about captures and ko.
+    pub fn clear(&mut self) {
+        *self = Board {
+            size: self.size,
+            points: vec![None; self.points.len()],
+            ko: None,
+            last_move: None,
+            black_captures: 0,
+            white_captures: 0,
+        };
+    }
+
+    /// Is the board completely empty?
+    pub fn is_empty(&self) -> bool {
+        self.points.iter().all(Option::is_none)
+    }
+
+    /// Get the stone at a particular vertex, if there is one.
+    pub fn get(&self, vertex: Vertex) -> Option<Color> {
+        self.index(vertex).and_then(|ix| self.points[ix])
+    }
+
+    /// The point which the player whose stone was just captured can't
+    /// immediately retake because of ko, if any.
+    pub fn ko(&self) -> Option<Vertex> {
+        self.ko.map(|(ix, _)| self.vertex(ix))
+    }
+
+    /// The last move played (including passes), if any.
+    pub fn last_move(&self) -> Option<Move> {
+        self.last_move
+    }
+
+    /// How many stones has `color` captured?
+    pub fn captures(&self, color: Color) -> u32 {
+        match color {
+            Color::Black => self.black_captures,
+            Color::White => self.white_captures,
+        }
+    }
+
+    /// Every vertex on the board with a `color` stone on it.
+    pub fn stones(&self, color: Color) -> Vec<Vertex> {
+        (0..self.points.len())
+            .filter(|&ix| self.points[ix] == Some(color))
+            .map(|ix| self.vertex(ix))
+            .collect()
+    }
+
+    /// Would `play()` accept this move?
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.clone().play(mv).is_ok()
+    }
+
+    /// Play a move, removing any stones it captures.
+    ///
+    /// Passing is always allowed. Anything else fails with `illegal move` if
+    /// the point is occupied or off the board, if it's suicide, or if it
+    /// retakes a ko. The board is left untouched when a move is rejected.
+    pub fn play(&mut self, mv: Move) -> CommandResult<()> {
+        let ix = match mv.vertex {
+            Vertex::Pass => {
+                self.ko = None;
+                self.last_move = Some(mv);
+                return Ok(());
+            }
+            Vertex::Resign => return Err(ILLEGAL_MOVE.to_string()),
+            vertex => self.index(vertex).ok_or_else(|| ILLEGAL_MOVE.to_string())?,
+        };
+
+        if self.points[ix].is_some() || self.ko == Some((ix, mv.color)) {
+            return Err(ILLEGAL_MOVE.to_string());
+        }
+
+        self.points[ix] = Some(mv.color);
+
+        let mut captured = Vec::new();
+        let opponent = Some(mv.color.opponent());
+
+        for neighbour in self.neighbours(ix) {
+            if self.points[neighbour] == opponent && !captured.contains(&neighbour) {
+                let (group, liberties) = self.group(neighbour);
+                if liberties == 0 {
+                    captured.extend(group);
+                }
+            }
+        }
+
+        let (group, liberties) = self.group(ix);
+
+        if captured.is_empty() && liberties == 0 {
+            // suicide
+            self.points[ix] = None;
+            return Err(ILLEGAL_MOVE.to_string());
+        }
+
+        for &stone in &captured {
+            self.points[stone] = None;
+        }
+
+        // taking a single stone with a single stone which is then left in
+        // atari means the opponent can't immediately take back
+        self.ko = if captured.len() == 1 && group.len() == 1 && self.group(ix).1 == 1 {
+            Some((captured[0], mv.color.opponent()))
+        } else {
+            None
+        };
+
+        match mv.color {
+            Color::Black => self.black_captures += captured.len() as u32,
+            Color::White => self.white_captures += captured.len() as u32,
+        }
+
+        self.last_move = Some(mv);
+        Ok(())
+    }
+
+    /// Place black handicap stones on the spec's fixed handicap points,
+    /// returning where they went.
+    ///
+    /// Fails with `board not empty` or `invalid number of stones`.
+    pub fn fixed_handicap(&mut self, stones: u32) -> CommandResult<Vec<Vertex>> {
+        if !self.is_empty() {
+            return Err(BOARD_NOT_EMPTY.to_string());
+        }
+
+        let vertices = handicap::fixed_placement(self.size(), stones)?;
+        self.add_stones(Color::Black, &vertices)?;
+        Ok(vertices)
+    }
+
+    /// Place black handicap stones wherever we like, returning where they
+    /// went.
+    ///
+    /// Any number of stones from 2 up to one less than the number of points
+    /// on the board is accepted, but (as the spec allows) no more than the
+    /// fixed handicap points will actually be placed. Fails with `board not
+    /// empty` or `invalid number of stones`.
+    pub fn place_free_handicap(&mut self, stones: u32) -> CommandResult<Vec<Vertex>> {
+        if !self.is_empty() {
+            return Err(BOARD_NOT_EMPTY.to_string());
+        }
+        if stones < 2 || stones as usize >= self.points.len() {
+            return Err(INVALID_NUMBER_OF_STONES.to_string());
+        }
+
+        let max = handicap::max_fixed_handicap(self.size());
+        let vertices = if max >= 2 {
+            handicap::fixed_placement(self.size(), stones.min(max))?
+        } else {
+            // too small for the fixed points, so just use the corners
+            let high = self.size - 1;
+            vec![Vertex::Point { column: 0, row: 0 }, Vertex::Point { column: high, row: high }]
+        };
+
+        self.add_stones(Color::Black, &vertices)?;
+        Ok(vertices)
+    }
+
+    /// Place black handicap stones on the provided vertices.
+    ///
+    /// Fails with `board not empty`, or `bad vertex list` if there are fewer
+    /// than 2 vertices, the board would be full, or any are repeated or off
+    /// the board.
+    pub fn set_free_handicap(&mut self, vertices: &[Vertex]) -> CommandResult<()> {
+        if !self.is_empty() {
+            return Err(BOARD_NOT_EMPTY.to_string());
+        }
+
+        let mut indices = Vec::with_capacity(vertices.len());
+
+        for &vertex in vertices {
+            match self.index(vertex) {
+                Some(ix) if !indices.contains(&ix) => indices.push(ix),
+                _ => return Err(BAD_VERTEX_LIST.to_string()),
+            }
+        }
+
+        if indices.len() < 2 || indices.len() >= self.points.len() {
+            return Err(BAD_VERTEX_LIST.to_string());
+        }
+
+        self.add_stones(Color::Black, vertices)
+    }
+
+    /// Put stones on the board without playing them as moves (e.g. an SGF
+    /// file's `AB` and `AW` setup), so nothing gets captured.
+    ///
+    /// Fails with `illegal move`, leaving the board untouched, if any of the
+    /// vertices are off the board, occupied, or repeated.
+    pub fn add_stones(&mut self, color: Color, vertices: &[Vertex]) -> CommandResult<()> {
+        let mut indices = Vec::with_capacity(vertices.len());
+
+        for &vertex in vertices {
+            match self.index(vertex) {
+                Some(ix) if self.points[ix].is_none() && !indices.contains(&ix) => indices.push(ix),
+                _ => return Err(ILLEGAL_MOVE.to_string()),
+            }
+        }
+
+        for ix in indices {
+            self.points[ix] = Some(color);
+        }
+
+        self.ko = None;
+        Ok(())
+    }
+
+    pub(crate) fn index(&self, vertex: Vertex) -> Option<usize> {
+        match vertex.coords() {
+            Some((column, row)) if column < self.size && row < self.size => {
+                Some(usize::from(row) * usize::from(self.size) + usize::from(column))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn vertex(&self, ix: usize) -> Vertex {
+        let size = usize::from(self.size);
+        Vertex::Point {
+            column: (ix % size) as u8,
+            row: (ix / size) as u8,
+        }
+    }
+
+    pub(crate) fn neighbours(&self, ix: usize) -> Vec<usize> {
+        let size = usize::from(self.size);
+        let (column, row) = (ix % size, ix / size);
+        let mut neighbours = Vec::with_capacity(4);
+
+        if column > 0 {
+            neighbours.push(ix - 1);
+        }
+        if column + 1 < size {
+            neighbours.push(ix + 1);
+        }
+        if row > 0 {
+            neighbours.push(ix - size);
+        }
+        if row + 1 < size {
+            neighbours.push(ix + size);
+        }
+
+        neighbours
+    }
+
+    /// Find the group of stones connected to `ix`, along with how many
+    /// liberties it has.
+    fn group(&self, ix: usize) -> (Vec<usize>, usize) {
+        let color = self.points[ix];
+        let mut stones = vec![ix];
+        let mut liberties = Vec::new();
+        let mut next = 0;
+
+        while next < stones.len() {
+            for neighbour in self.neighbours(stones[next]) {
+                if self.points[neighbour].is_none() {
+                    if !liberties.contains(&neighbour) {
+                        liberties.push(neighbour);
+                    }
+                } else if self.points[neighbour] == color && !stones.contains(&neighbour) {
+                    stones.push(neighbour);
+                }
+            }
+
+            next += 1;
+        }
+
+        (stones, liberties.len())
+    }
+}
+
+impl Display for Board {
+    /// Draw the board in the same format as GNU Go's `showboard`, with `X`
+    /// for black, `O` for white, `+` for hoshi, and the last move in
+    /// parentheses.
+    ///
+    /// Use `format!("\n{}", board)` as the `showboard` response so the
+    /// diagram starts on its own line.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let size = usize::from(self.size);
+        let letters: Vec<String> = COLUMN_LETTERS[..size]
+            .iter()
+            .map(|&c| (c as char).to_string())
+            .collect();
+        let header = format!("   {}", letters.join(" "));
+        let last_move = self.last_move.and_then(|mv| self.index(mv.vertex));
+
+        writeln!(f, "{}", header)?;
+
+        for i in 0..size {
+            let row = size - 1 - i;
+            write!(f, "{:2}", row + 1)?;
+
+            for column in 0..size {
+                let ix = row * size + column;
+
+                let separator = if Some(ix) == last_move {
+                    '('
+                } else if column > 0 && Some(ix - 1) == last_move {
+                    ')'
+                } else {
+                    ' '
+                };
+
+                let point = match self.points[ix] {
+                    Some(Color::Black) => 'X',
+                    Some(Color::White) => 'O',
+                    None if is_hoshi(size, column, row) => '+',
+                    None => '.',
+                };
+
+                write!(f, "{}{}", separator, point)?;
+            }
+
+            let end = if Some(row * size + size - 1) == last_move { ')' } else { ' ' };
+            write!(f, "{}{}", end, row + 1)?;
+
+            // GNU Go puts the capture counts next to the 9th and 10th rows
+            // from the top, or the bottom two rows on small boards
+            let (white_row, black_row) = if size < 10 { (size - 2, size - 1) } else { (8, 9) };
+
+            if i == white_row {
+                write!(f, "     WHITE (O) has captured {}", stones(self.white_captures))?;
+            } else if i == black_row {
+                write!(f, "     BLACK (X) has captured {}", stones(self.black_captures))?;
+            }
+
+            writeln!(f)?;
+        }
+
+        write!(f, "{}", header)
+    }
+}
+
+fn stones(count: u32) -> String {
+    if count == 1 {
+        "1 stone".to_string()
+    } else {
+        format!("{} stones", count)
+    }
+}
+
+/// Is this one of the board's star points?
+///
+/// Like GNU Go, 3-3 points are used on boards up to 11x11 and 4-4 points on
+/// anything larger, with tengen on odd sizes and the side star points from
+/// 13x13 up. Boards smaller than 7x7 only get tengen.
+fn is_hoshi(size: usize, column: usize, row: usize) -> bool {
+    let middle = size / 2;
+    let is_middle = |x: usize| size % 2 == 1 && x == middle;
+
+    if size < 7 {
+        return is_middle(column) && is_middle(row);
+    }
+
+    let edge = if size <= 11 { 2 } else { 3 };
+    let on_line = |x: usize| x == edge || x == size - 1 - edge;
+
+    (on_line(column) && on_line(row)) || (is_middle(column) && is_middle(row)) ||
+    (size >= 13 && ((on_line(column) && is_middle(row)) || (is_middle(column) && on_line(row))))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(board: &mut Board, color: Color, vertices: &[&str]) {
+        for vertex in vertices {
+            board.play(Move::new(color, vertex.parse().unwrap())).unwrap();
+        }
+    }
+
+    fn vertex(s: &str) -> Vertex {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn board_sizes() {
+        assert!(Board::new(1).is_err());
+        assert_eq!(Board::new(26).unwrap_err(), "unacceptable size");
+        assert_eq!(Board::new(2).unwrap().size(), 2);
+        assert_eq!(Board::new(25).unwrap().size(), 25);
+    }
+
+    #[test]
+    fn moves_off_the_board_are_illegal() {
+        let mut board = Board::new(9).unwrap();
+
+        let err = board.play(Move::new(Color::Black, vertex("K10"))).unwrap_err();
+
+        assert_eq!(err, "illegal move");
+        assert!(board.is_empty());
+    }
+
+    #[test]
+    fn capture_a_stone_in_the_corner() {
+        let mut board = Board::new(9).unwrap();
+        play(&mut board, Color::White, &["A1"]);
+        play(&mut board, Color::Black, &["A2", "B1"]);
+
+        assert_eq!(board.get(vertex("A1")), None);
+        assert_eq!(board.captures(Color::Black), 1);
+        assert_eq!(board.captures(Color::White), 0);
+    }
+
+    #[test]
+    fn capture_a_group() {
+        let mut board = Board::new(9).unwrap();
+        play(&mut board, Color::White, &["D4", "D5"]);
+        play(&mut board, Color::Black, &["C4", "C5", "E4", "E5", "D3", "D6"]);
+
+        assert_eq!(board.stones(Color::White), vec![]);
+        assert_eq!(board.captures(Color::Black), 2);
+        // capturing two stones doesn't create a ko
+        assert_eq!(board.ko(), None);
+    }
+
+    #[test]
+    fn suicide_is_illegal() {
+        let mut board = Board::new(9).unwrap();
+        play(&mut board, Color::Black, &["A2", "B1"]);
+        let before = board.clone();
+
+        assert!(!board.is_legal(Move::new(Color::White, vertex("A1"))));
+        assert!(board.play(Move::new(Color::White, vertex("A1"))).is_err());
+        assert_eq!(board, before);
+    }
+
+    #[test]
+    fn capturing_takes_precedence_over_suicide() {
+        let mut board = Board::new(9).unwrap();
+        play(&mut board, Color::Black, &["B1", "A2"]);
+        play(&mut board, Color::White, &["C1", "B2", "A3"]);
+
+        // white fills black's last liberty, capturing both stones
+        play(&mut board, Color::White, &["A1"]);
+
+        assert_eq!(board.captures(Color::White), 2);
+        assert_eq!(board.stones(Color::Black), vec![]);
+    }
+
+    #[test]
+    fn simple_ko() {
+        let mut board = Board::new(9).unwrap();
+        play(&mut board, Color::Black, &["C5", "D4", "D6"]);
+        play(&mut board, Color::White, &["E4", "E6", "F5", "D5"]);
+
+        // black takes the ko
+        play(&mut board, Color::Black, &["E5"]);
+        assert_eq!(board.get(vertex("D5")), None);
+        assert_eq!(board.ko(), Some(vertex("D5")));
+
+        // white can't immediately take back
+        let err = board.play(Move::new(Color::White, vertex("D5"))).unwrap_err();
+        assert_eq!(err, "illegal move");
+
+        // but can after a ko threat (or a pass)
+        board.play(Move::new(Color::White, Vertex::Pass)).unwrap();
+        play(&mut board, Color::Black, &["A1"]);
+        play(&mut board, Color::White, &["D5"]);
+        assert_eq!(board.get(vertex("E5")), None);
+        assert_eq!(board.ko(), Some(vertex("E5")));
+    }
+
+    #[test]
+    fn the_capturer_can_fill_the_ko() {
+        let mut board = Board::new(9).unwrap();
+        play(&mut board, Color::Black, &["C5", "D4", "D6"]);
+        play(&mut board, Color::White, &["E4", "E6", "F5", "D5"]);
+        play(&mut board, Color::Black, &["E5"]);
+        assert_eq!(board.ko(), Some(vertex("D5")));
+
+        // only white is stopped from playing there
+        assert!(!board.is_legal(Move::new(Color::White, vertex("D5"))));
+        play(&mut board, Color::Black, &["D5"]);
+
+        assert_eq!(board.get(vertex("D5")), Some(Color::Black));
+        assert_eq!(board.ko(), None);
+    }
+
+    #[test]
+    fn clearing_the_board() {
+        let mut board = Board::new(5).unwrap();
+        play(&mut board, Color::White, &["A1"]);
+        play(&mut board, Color::Black, &["A2", "B1"]);
+
+        board.clear();
+
+        assert!(board.is_empty());
+        assert_eq!(board.captures(Color::Black), 0);
+        assert_eq!(board, Board::new(5).unwrap());
+    }
+
+    #[test]
+    fn show_an_empty_board() {
+        let board = Board::new(9).unwrap();
+        let should_be = "   A B C D E F G H J
+ 9 . . . . . . . . . 9
+ 8 . . . . . . . . . 8
+ 7 . . + . . . + . . 7
+ 6 . . . . . . . . . 6
+ 5 . . . . + . . . . 5
+ 4 . . . . . . . . . 4
+ 3 . . + . . . + . . 3
+ 2 . . . . . . . . . 2     WHITE (O) has captured 0 stones
+ 1 . . . . . . . . . 1     BLACK (X) has captured 0 stones
+   A B C D E F G H J";
+
+        assert_eq!(board.to_string(), should_be);
+    }
+
+    #[test]
+    fn show_a_game_in_progress() {
+        let mut board = Board::new(19).unwrap();
+        play(&mut board, Color::Black, &["Q16", "D4", "C17", "R1", "S2"]);
+        play(&mut board, Color::White, &["Q4", "D16", "S1"]);
+        play(&mut board, Color::Black, &["T1"]);
+
+        let should_be = "   A B C D E F G H J K L M N O P Q R S T
+19 . . . . . . . . . . . . . . . . . . . 19
+18 . . . . . . . . . . . . . . . . . . . 18
+17 . . X . . . . . . . . . . . . . . . . 17
+16 . . . O . . . . . + . . . . . X . . . 16
+15 . . . . . . . . . . . . . . . . . . . 15
+14 . . . . . . . . . . . . . . . . . . . 14
+13 . . . . . . . . . . . . . . . . . . . 13
+12 . . . . . . . . . . . . . . . . . . . 12
+11 . . . . . . . . . . . . . . . . . . . 11     WHITE (O) has captured 0 stones
+10 . . . + . . . . . + . . . . . + . . . 10     BLACK (X) has captured 1 stone
+ 9 . . . . . . . . . . . . . . . . . . . 9
+ 8 . . . . . . . . . . . . . . . . . . . 8
+ 7 . . . . . . . . . . . . . . . . . . . 7
+ 6 . . . . . . . . . . . . . . . . . . . 6
+ 5 . . . . . . . . . . . . . . . . . . . 5
+ 4 . . . X . . . . . + . . . . . O . . . 4
+ 3 . . . . . . . . . . . . . . . . . . . 3
+ 2 . . . . . . . . . . . . . . . . . X . 2
+ 1 . . . . . . . . . . . . . . . . X .(X)1
+   A B C D E F G H J K L M N O P Q R S T";
+
+        assert_eq!(board.to_string(), should_be);
+    }
+
+    #[test]
+    fn hoshi_points() {
+        let hoshi = |size: usize| {
+            let mut points = Vec::new();
+            for row in 0..size {
+                for column in 0..size {
+                    if is_hoshi(size, column, row) {
+                        points.push(Vertex::Point { column: column as u8, row: row as u8 }
+                            .to_string());
+                    }
+                }
+            }
+            points
+        };
+
+        assert!(hoshi(4).is_empty());
+        assert_eq!(hoshi(5), vec!["C3"]);
+        assert_eq!(hoshi(8), vec!["C3", "F3", "C6", "F6"]);
+        assert_eq!(hoshi(13).len(), 9);
+        assert_eq!(hoshi(19),
+                   vec!["D4", "K4", "Q4", "D10", "K10", "Q10", "D16", "K16", "Q16"]);
+    }
+
+    #[test]
+    fn fixed_handicap() {
+        let mut board = Board::new(19).unwrap();
+
+        let got = board.fixed_handicap(3).unwrap();
+
+        assert_eq!(got, vec![vertex("D4"), vertex("Q16"), vertex("D16")]);
+        assert_eq!(board.stones(Color::Black).len(), 3);
+        assert_eq!(board.last_move(), None);
+        assert_eq!(board.fixed_handicap(2).unwrap_err(), "board not empty");
+    }
+
+    #[test]
+    fn place_free_handicap() {
+        let mut board = Board::new(9).unwrap();
+        assert_eq!(board.place_free_handicap(81).unwrap_err(), "invalid number of stones");
+        assert_eq!(board.place_free_handicap(1).unwrap_err(), "invalid number of stones");
+
+        let got = board.place_free_handicap(20).unwrap();
+
+        assert_eq!(got.len(), 9);
+        assert_eq!(board.stones(Color::Black).len(), 9);
+
+        let mut tiny = Board::new(3).unwrap();
+        assert_eq!(tiny.place_free_handicap(4).unwrap(), vec![vertex("A1"), vertex("C3")]);
+    }
+
+    #[test]
+    fn set_free_handicap() {
+        let mut board = Board::new(9).unwrap();
+        let bad_lists = vec![vec!["D4"], vec!["D4", "D4"], vec!["D4", "K10"]];
+
+        for list in bad_lists {
+            let vertices: Vec<Vertex> = list.iter().map(|v| vertex(v)).collect();
+            assert_eq!(board.set_free_handicap(&vertices).unwrap_err(), "bad vertex list");
+            assert!(board.is_empty());
+        }
+
+        board.set_free_handicap(&[vertex("A1"), vertex("J9"), vertex("E5")]).unwrap();
+        assert_eq!(board.get(vertex("E5")), Some(Color::Black));
+        assert_eq!(board.set_free_handicap(&[vertex("B2"), vertex("C3")]).unwrap_err(),
+                   "board not empty");
+    }
+}
@@ -0,0 +1,526 @@
+//! Strongly typed versions of every command in the GTP v2 specification.
+//!
+//! A `StandardCommand` can be created from a `RawCommand` using `TryFrom`,
+//! which will make sure the command has the right number of arguments and
+//! that each argument is of the right type.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use go_text_protocol::{Color, Move, Vertex, parse};
+//! use go_text_protocol::commands::StandardCommand;
+//!
+//! let cmd: StandardCommand = parse("play white Q16").unwrap();
+//!
+//! let should_be = Move::new(Color::White, Vertex::Point { column: 15, row: 15 });
+//! assert_eq!(cmd, StandardCommand::Play(should_be));
+//! assert_eq!(cmd.to_string(), "play white Q16");
+//!
+//! // Commands with the wrong arguments are rejected
+//! assert!(parse::<StandardCommand>("boardsize nineteen").is_err());
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+
+use crate::errors::*;
+use crate::parser::RawCommand;
+use crate::values::{Color, Float, Int, Move, StoneStatus, Vertex};
+
+/// A set of commands which can be parsed from a `RawCommand` and written back
+/// out in their wire format.
+///
+/// The easiest way to implement this is with `#[derive(GtpCommand)]`, which
+/// also generates the `TryFrom<RawCommand>` and `Display` impls. Each variant
+/// is a command, and its fields are the command's arguments (parsed using
+/// `FromStr` and written using `Display`). The following attributes are
+/// understood:
+///
+/// - `#[gtp(name = "...")]` on a variant sets the command's wire name,
+///   otherwise the variant name is converted to `snake_case`
+/// - `#[gtp(id)]` on an `Option<u32>` field captures the command's id
+/// - `#[gtp(rest)]` on the last field collects all remaining arguments into a
+///   `Vec`
+/// - `#[gtp(other)]` on a variant with a single `RawCommand` field catches
+///   any unknown commands, without it they are an `ErrorKind::UnknownCommand`
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate go_text_protocol;
+///
+/// use go_text_protocol::{Color, GtpCommand, RawCommand, Vertex, parse};
+///
+/// #[derive(Debug, PartialEq, GtpCommand)]
+/// enum MyCommand {
+///     /// Place a stone.
+///     Play(#[gtp(id)] Option<u32>, Color, Vertex),
+///     #[gtp(name = "showboard")]
+///     ShowBoard,
+///     #[gtp(name = "gogui-analyze_commands")]
+///     AnalyzeCommands,
+///     #[gtp(name = "set_free_handicap")]
+///     SetFreeHandicap(#[gtp(rest)] Vec<Vertex>),
+///     #[gtp(other)]
+///     Other(RawCommand),
+/// }
+///
+/// fn main() {
+///     let got: MyCommand = parse("4 play white pass").unwrap();
+///     assert_eq!(got, MyCommand::Play(Some(4), Color::White, Vertex::Pass));
+///     assert_eq!(got.to_string(), "4 play white pass");
+///
+///     let got: MyCommand = parse("gogui-analyze_commands").unwrap();
+///     assert_eq!(got, MyCommand::AnalyzeCommands);
+///
+///     assert_eq!(MyCommand::COMMAND_NAMES,
+///                &["play", "showboard", "gogui-analyze_commands", "set_free_handicap"]);
+/// }
+/// ```
+pub trait GtpCommand: TryFrom<RawCommand, Error = Error> + Display {
+    /// The wire names of every command this type knows about (e.g. for
+    /// responding to `list_commands`).
+    const COMMAND_NAMES: &'static [&'static str];
+}
+
+/// Every command defined by the GTP v2 specification, along with its
+/// arguments.
+#[derive(Clone, PartialEq, Debug)]
+pub enum StandardCommand {
+    /// `protocol_version`
+    ProtocolVersion,
+    /// `name`
+    Name,
+    /// `version`
+    Version,
+    /// `known_command command_name`
+    KnownCommand(String),
+    /// `list_commands`
+    ListCommands,
+    /// `quit`
+    Quit,
+    /// `boardsize size`
+    BoardSize(u32),
+    /// `clear_board`
+    ClearBoard,
+    /// `komi new_komi`
+    Komi(f32),
+    /// `fixed_handicap number_of_stones`
+    FixedHandicap(u32),
+    /// `place_free_handicap number_of_stones`
+    PlaceFreeHandicap(u32),
+    /// `set_free_handicap vertex*`
+    SetFreeHandicap(Vec<Vertex>),
+    /// `play move`
+    Play(Move),
+    /// `genmove color`
+    GenMove(Color),
+    /// `undo`
+    Undo,
+    /// `time_settings main_time byo_yomi_time byo_yomi_stones`
+    TimeSettings {
+        /// Main time in seconds.
+        main_time: u32,
+        /// Byo-yomi time in seconds.
+        byo_yomi_time: u32,
+        /// The number of stones which must be played per byo-yomi period.
+        byo_yomi_stones: u32,
+    },
+    /// `time_left color time stones`
+    TimeLeft {
+        /// The player whose clock is being updated.
+        color: Color,
+        /// The number of seconds remaining.
+        time: u32,
+        /// The number of stones remaining in this byo-yomi period, or `0` if
+        /// still in main time.
+        stones: u32,
+    },
+    /// `final_score`
+    FinalScore,
+    /// `final_status_list status`
+    FinalStatusList(StoneStatus),
+    /// `loadsgf filename [move_number]`
+    LoadSgf {
+        /// The file to load.
+        filename: String,
+        /// Only play moves up to (but not including) this move number.
+        move_number: Option<u32>,
+    },
+    /// `reg_genmove color`
+    RegGenMove(Color),
+    /// `showboard`
+    ShowBoard,
+}
+
+impl StandardCommand {
+    /// The names of all standard commands, in the order they appear in the
+    /// specification.
+    pub const NAMES: &'static [&'static str] = &["protocol_version",
+                                                 "name",
+                                                 "version",
+                                                 "known_command",
+                                                 "list_commands",
+                                                 "quit",
+                                                 "boardsize",
+                                                 "clear_board",
+                                                 "komi",
+                                                 "fixed_handicap",
+                                                 "place_free_handicap",
+                                                 "set_free_handicap",
+                                                 "play",
+                                                 "genmove",
+                                                 "undo",
+                                                 "time_settings",
+                                                 "time_left",
+                                                 "final_score",
+                                                 "final_status_list",
+                                                 "loadsgf",
+                                                 "reg_genmove",
+                                                 "showboard"];
+
+    /// The name used when sending this command over the wire.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            StandardCommand::ProtocolVersion => "protocol_version",
+            StandardCommand::Name => "name",
+            StandardCommand::Version => "version",
+            StandardCommand::KnownCommand(_) => "known_command",
+            StandardCommand::ListCommands => "list_commands",
+            StandardCommand::Quit => "quit",
+            StandardCommand::BoardSize(_) => "boardsize",
+            StandardCommand::ClearBoard => "clear_board",
+            StandardCommand::Komi(_) => "komi",
+            StandardCommand::FixedHandicap(_) => "fixed_handicap",
+            StandardCommand::PlaceFreeHandicap(_) => "place_free_handicap",
+            StandardCommand::SetFreeHandicap(_) => "set_free_handicap",
+            StandardCommand::Play(_) => "play",
+            StandardCommand::GenMove(_) => "genmove",
+            StandardCommand::Undo => "undo",
+            StandardCommand::TimeSettings { .. } => "time_settings",
+            StandardCommand::TimeLeft { .. } => "time_left",
+            StandardCommand::FinalScore => "final_score",
+            StandardCommand::FinalStatusList(_) => "final_status_list",
+            StandardCommand::LoadSgf { .. } => "loadsgf",
+            StandardCommand::RegGenMove(_) => "reg_genmove",
+            StandardCommand::ShowBoard => "showboard",
+        }
+    }
+
+    /// The command's arguments, formatted as they would be sent over the
+    /// wire.
+    pub fn args(&self) -> Vec<String> {
+        match *self {
+            StandardCommand::KnownCommand(ref name) => vec![name.clone()],
+            StandardCommand::BoardSize(size) => vec![size.to_string()],
+            StandardCommand::Komi(komi) => vec![komi.to_string()],
+            StandardCommand::FixedHandicap(n) |
+            StandardCommand::PlaceFreeHandicap(n) => vec![n.to_string()],
+            StandardCommand::SetFreeHandicap(ref vertices) => {
+                vertices.iter().map(|v| v.to_string()).collect()
+            }
+            StandardCommand::Play(mv) => vec![mv.color.to_string(), mv.vertex.to_string()],
+            StandardCommand::GenMove(color) |
+            StandardCommand::RegGenMove(color) => vec![color.to_string()],
+            StandardCommand::TimeSettings { main_time, byo_yomi_time, byo_yomi_stones } => {
+                vec![main_time.to_string(),
+                     byo_yomi_time.to_string(),
+                     byo_yomi_stones.to_string()]
+            }
+            StandardCommand::TimeLeft { color, time, stones } => {
+                vec![color.to_string(), time.to_string(), stones.to_string()]
+            }
+            StandardCommand::FinalStatusList(status) => vec![status.to_string()],
+            StandardCommand::LoadSgf { ref filename, move_number } => {
+                let mut args = vec![filename.clone()];
+                args.extend(move_number.map(|n| n.to_string()));
+                args
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn int_arg(raw: &RawCommand, index: usize) -> Result<u32> {
+    raw.arg::<Int>(index).map(|n| n.0)
+}
+
+/// Parse an argument as a point on the board, rejecting `pass` and `resign`.
+fn point_arg(raw: &RawCommand, index: usize) -> Result<Vertex> {
+    match raw.arg(index)? {
+        vertex @ Vertex::Point { .. } => Ok(vertex),
+        _ => {
+            let arg = raw.args[index].clone();
+            let reason = Error::from(ErrorKind::InvalidValue("board point", arg.clone()));
+            Err(Error::with_source(ErrorKind::InvalidArgument(index, arg), reason))
+        }
+    }
+}
+
+impl GtpCommand for StandardCommand {
+    const COMMAND_NAMES: &'static [&'static str] = StandardCommand::NAMES;
+}
+
+impl TryFrom<RawCommand> for StandardCommand {
+    type Error = Error;
+
+    fn try_from(raw: RawCommand) -> Result<StandardCommand> {
+        StandardCommand::try_from(&raw)
+    }
+}
+
+impl<'a> TryFrom<&'a RawCommand> for StandardCommand {
+    type Error = Error;
+
+    fn try_from(raw: &'a RawCommand) -> Result<StandardCommand> {
+        let name = raw.name.to_lowercase();
+
+        let (min, max) = match name.as_str() {
+            "known_command" | "boardsize" | "komi" | "fixed_handicap" |
+            "place_free_handicap" | "genmove" | "final_status_list" | "reg_genmove" => (1, 1),
+            "play" => (2, 2),
+            "time_settings" | "time_left" => (3, 3),
+            "loadsgf" => (1, 2),
+            "set_free_handicap" => (1, usize::MAX),
+            _ => (0, 0),
+        };
+
+        if !StandardCommand::NAMES.contains(&name.as_str()) {
+            bail!(ErrorKind::UnknownCommand(raw.name.clone()));
+        }
+        raw.check_arg_count(min, max)?;
+
+        let cmd = match name.as_str() {
+            "protocol_version" => StandardCommand::ProtocolVersion,
+            "name" => StandardCommand::Name,
+            "version" => StandardCommand::Version,
+            "known_command" => StandardCommand::KnownCommand(raw.args[0].clone()),
+            "list_commands" => StandardCommand::ListCommands,
+            "quit" => StandardCommand::Quit,
+            "boardsize" => StandardCommand::BoardSize(int_arg(raw, 0)?),
+            "clear_board" => StandardCommand::ClearBoard,
+            "komi" => StandardCommand::Komi(raw.arg::<Float>(0)?.0),
+            "fixed_handicap" => StandardCommand::FixedHandicap(int_arg(raw, 0)?),
+            "place_free_handicap" => StandardCommand::PlaceFreeHandicap(int_arg(raw, 0)?),
+            "set_free_handicap" => {
+                let vertices = (0..raw.args.len())
+                    .map(|i| point_arg(raw, i))
+                    .collect::<Result<_>>()?;
+                StandardCommand::SetFreeHandicap(vertices)
+            }
+            "play" => StandardCommand::Play(raw.move_arg(0)?),
+            "genmove" => StandardCommand::GenMove(raw.arg(0)?),
+            "undo" => StandardCommand::Undo,
+            "time_settings" => {
+                StandardCommand::TimeSettings {
+                    main_time: int_arg(raw, 0)?,
+                    byo_yomi_time: int_arg(raw, 1)?,
+                    byo_yomi_stones: int_arg(raw, 2)?,
+                }
+            }
+            "time_left" => {
+                StandardCommand::TimeLeft {
+                    color: raw.arg(0)?,
+                    time: int_arg(raw, 1)?,
+                    stones: int_arg(raw, 2)?,
+                }
+            }
+            "final_score" => StandardCommand::FinalScore,
+            "final_status_list" => StandardCommand::FinalStatusList(raw.arg(0)?),
+            "loadsgf" => {
+                let move_number = if raw.args.len() > 1 {
+                    Some(int_arg(raw, 1)?)
+                } else {
+                    None
+                };
+
+                StandardCommand::LoadSgf {
+                    filename: raw.args[0].clone(),
+                    move_number,
+                }
+            }
+            "reg_genmove" => StandardCommand::RegGenMove(raw.arg(0)?),
+            "showboard" => StandardCommand::ShowBoard,
+            _ => unreachable!(),
+        };
+
+        Ok(cmd)
+    }
+}
+
+impl Display for StandardCommand {
+    /// Write the command in its wire format (without an id or trailing
+    /// newline).
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+
+        for arg in self.args() {
+            write!(f, " {}", arg)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::GtpCommand;
+
+    fn d4() -> Vertex {
+        Vertex::Point { column: 3, row: 3 }
+    }
+
+    #[test]
+    fn parse_every_standard_command() {
+        let inputs = vec![("protocol_version", StandardCommand::ProtocolVersion),
+                          ("name", StandardCommand::Name),
+                          ("version", StandardCommand::Version),
+                          ("known_command play", StandardCommand::KnownCommand("play".to_string())),
+                          ("list_commands", StandardCommand::ListCommands),
+                          ("quit", StandardCommand::Quit),
+                          ("boardsize 19", StandardCommand::BoardSize(19)),
+                          ("clear_board", StandardCommand::ClearBoard),
+                          ("komi 6.5", StandardCommand::Komi(6.5)),
+                          ("fixed_handicap 4", StandardCommand::FixedHandicap(4)),
+                          ("place_free_handicap 3", StandardCommand::PlaceFreeHandicap(3)),
+                          ("set_free_handicap D4 Q16",
+                           StandardCommand::SetFreeHandicap(vec![d4(),
+                                                                 Vertex::Point {
+                                                                     column: 15,
+                                                                     row: 15,
+                                                                 }])),
+                          ("play b D4", StandardCommand::Play(Move::new(Color::Black, d4()))),
+                          ("genmove white", StandardCommand::GenMove(Color::White)),
+                          ("undo", StandardCommand::Undo),
+                          ("time_settings 300 30 5",
+                           StandardCommand::TimeSettings {
+                               main_time: 300,
+                               byo_yomi_time: 30,
+                               byo_yomi_stones: 5,
+                           }),
+                          ("time_left B 25 3",
+                           StandardCommand::TimeLeft {
+                               color: Color::Black,
+                               time: 25,
+                               stones: 3,
+                           }),
+                          ("final_score", StandardCommand::FinalScore),
+                          ("final_status_list dead",
+                           StandardCommand::FinalStatusList(StoneStatus::Dead)),
+                          ("loadsgf game.sgf",
+                           StandardCommand::LoadSgf {
+                               filename: "game.sgf".to_string(),
+                               move_number: None,
+                           }),
+                          ("loadsgf game.sgf 12",
+                           StandardCommand::LoadSgf {
+                               filename: "game.sgf".to_string(),
+                               move_number: Some(12),
+                           }),
+                          ("reg_genmove w", StandardCommand::RegGenMove(Color::White)),
+                          ("showboard", StandardCommand::ShowBoard)];
+
+        assert_eq!(inputs.len(), StandardCommand::NAMES.len() + 1);
+
+        for (src, should_be) in inputs {
+            let got: StandardCommand = parse(src).unwrap();
+            assert_eq!(got, should_be, "{:?}", src);
+
+            // and they should round-trip back to the wire format
+            let round_tripped: StandardCommand = parse(&got.to_string()).unwrap();
+            assert_eq!(round_tripped, should_be);
+        }
+    }
+
+    #[test]
+    fn wrong_argument_count() {
+        let inputs = vec!["quit now", "boardsize", "play black", "play black D4 D5",
+                          "time_left black 5", "loadsgf a.sgf 1 2", "set_free_handicap"];
+
+        for src in inputs {
+            match *parse::<StandardCommand>(src).unwrap_err().kind() {
+                ErrorKind::WrongArgumentCount(..) => {}
+                ref other => panic!("Unexpected error for {:?}: {:?}", src, other),
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_argument_type() {
+        let inputs = vec!["boardsize nineteen", "komi a", "play D4 black", "genmove purple",
+                          "final_status_list undecided", "time_settings 1 -2 3",
+                          "set_free_handicap D4 I4", "set_free_handicap D4 pass",
+                          "set_free_handicap resign D4"];
+
+        for src in inputs {
+            match *parse::<StandardCommand>(src).unwrap_err().kind() {
+                ErrorKind::InvalidArgument(..) => {}
+                ref other => panic!("Unexpected error for {:?}: {:?}", src, other),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, GtpCommand)]
+    enum DerivedCommand {
+        Quit,
+        TimeLeft {
+            #[gtp(id)]
+            id: Option<u32>,
+            color: Color,
+            time: Int,
+            stones: Int,
+        },
+        #[gtp(name = "kgs-genmove_cleanup")]
+        GenMoveCleanup(Color),
+        Echo(#[gtp(rest)] Vec<String>),
+    }
+
+    #[test]
+    fn derived_commands() {
+        let got: DerivedCommand = parse("12 time_left b 30 2").unwrap();
+        let should_be = DerivedCommand::TimeLeft {
+            id: Some(12),
+            color: Color::Black,
+            time: Int(30),
+            stones: Int(2),
+        };
+        assert_eq!(got, should_be);
+        assert_eq!(got.to_string(), "12 time_left black 30 2");
+
+        let got: DerivedCommand = parse("KGS-genmove_cleanup w").unwrap();
+        assert_eq!(got, DerivedCommand::GenMoveCleanup(Color::White));
+
+        let got: DerivedCommand = parse("echo").unwrap();
+        assert_eq!(got, DerivedCommand::Echo(vec![]));
+        assert_eq!(got.to_string(), "echo");
+
+        assert_eq!(DerivedCommand::COMMAND_NAMES,
+                   &["quit", "time_left", "kgs-genmove_cleanup", "echo"]);
+    }
+
+    #[test]
+    fn derived_commands_validate_their_arguments() {
+        match *parse::<DerivedCommand>("quit now").unwrap_err().kind() {
+            ErrorKind::WrongArgumentCount(..) => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+        match *parse::<DerivedCommand>("time_left b 30 -2").unwrap_err().kind() {
+            ErrorKind::InvalidArgument(2, _) => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+        match *parse::<DerivedCommand>("showboard").unwrap_err().kind() {
+            ErrorKind::UnknownCommand(ref name) if name == "showboard" => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_command() {
+        match *parse::<StandardCommand>("kgs-genmove_cleanup b").unwrap_err().kind() {
+            ErrorKind::UnknownCommand(ref name) if name == "kgs-genmove_cleanup" => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! The controller side of a GTP session.
+//!
+//! A `Controller` sends commands to an engine (usually a subprocess like GNU
+//! Go or KataGo), giving each one a unique id and waiting for the matching
+//! response.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use std::process::Command;
+//! use go_text_protocol::{Color, Move};
+//! use go_text_protocol::controller::Controller;
+//!
+//! let mut gnugo = Controller::spawn(Command::new("gnugo").arg("--mode").arg("gtp")).unwrap();
+//!
+//! gnugo.boardsize(9).unwrap();
+//! gnugo.play(Move::new(Color::Black, "E5".parse().unwrap())).unwrap();
+//! let reply = gnugo.genmove(Color::White).unwrap();
+//!
+//! println!("GNU Go played {}", reply.vertex);
+//! gnugo.quit().unwrap();
+//! ```
+
+use std::fmt::Display;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::commands::StandardCommand;
+use crate::errors::*;
+use crate::response::{Response, read_response};
+use crate::values::{Boolean, Color, Int, Move, StoneStatus, Vertex};
+
+/// Something which sends commands to an engine and reads back its responses.
+pub struct Controller<W, R> {
+    writer: W,
+    reader: R,
+    next_id: u32,
+    child: Option<Child>,
+}
+
+/// A `Controller` talking to an engine subprocess.
+pub type ProcessController = Controller<ChildStdin, BufReader<ChildStdout>>;
+
+impl ProcessController {
+    /// Start an engine as a subprocess and talk to it over its `stdin` and
+    /// `stdout`.
+    pub fn spawn(command: &mut Command) -> Result<ProcessController> {
+        let mut child = command.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .chain_err(|| "Unable to start the engine")?;
+
+        let stdin = child.stdin.take().expect("stdin is always piped");
+        let stdout = child.stdout.take().expect("stdout is always piped");
+
+        let mut controller = Controller::new(stdin, BufReader::new(stdout));
+        controller.child = Some(child);
+
+        Ok(controller)
+    }
+}
+
+impl<W, R> Controller<W, R>
+    where W: Write,
+          R: BufRead
+{
+    /// Create a controller which talks to an engine over the provided
+    /// streams.
+    pub fn new(writer: W, reader: R) -> Controller<W, R> {
+        Controller {
+            writer,
+            reader,
+            next_id: 1,
+            child: None,
+        }
+    }
+
+    /// Send a command to the engine and wait for its response.
+    ///
+    /// The command is given a fresh id, and it's an error if the engine
+    /// replies with a different one. Note that failure responses (`?`) are
+    /// *not* turned into an error here.
+    pub fn send<C: Display>(&mut self, command: C) -> Result<Response> {
+        // ids only need to be unique among outstanding commands, so start
+        // again from 1 rather than overflowing
+        let id = self.next_id;
+        self.next_id = id.checked_add(1).unwrap_or(1);
+
+        writeln!(self.writer, "{} {}", id, command)?;
+        self.writer.flush()?;
+
+        let response = read_response(&mut self.reader)?;
+
+        if response.id != Some(id) {
+            bail!(ErrorKind::MismatchedResponse(id, response.id));
+        }
+
+        Ok(response)
+    }
+
+    /// Send a command, turning a failure response into an error.
+    pub fn execute<C: Display>(&mut self, command: C) -> Result<String> {
+        self.send(command)?.into_result()
+    }
+
+    /// Send one of the standard commands.
+    fn standard(&mut self, command: StandardCommand) -> Result<Response> {
+        self.send(command)
+    }
+
+    /// Ask which version of the protocol the engine speaks.
+    pub fn protocol_version(&mut self) -> Result<u32> {
+        self.standard(StandardCommand::ProtocolVersion)?.value::<Int>().map(Into::into)
+    }
+
+    /// Get the engine's name.
+    pub fn name(&mut self) -> Result<String> {
+        self.standard(StandardCommand::Name)?.into_result()
+    }
+
+    /// Get the engine's version.
+    pub fn version(&mut self) -> Result<String> {
+        self.standard(StandardCommand::Version)?.into_result()
+    }
+
+    /// Ask whether the engine knows a particular command.
+    pub fn known_command(&mut self, name: &str) -> Result<bool> {
+        self.standard(StandardCommand::KnownCommand(name.to_string()))?
+            .value::<Boolean>()
+            .map(Into::into)
+    }
+
+    /// Get the names of every command the engine knows.
+    pub fn list_commands(&mut self) -> Result<Vec<String>> {
+        let response = self.standard(StandardCommand::ListCommands)?.into_result()?;
+        Ok(response.lines().map(|line| line.trim().to_string()).collect())
+    }
+
+    /// Change the board size.
+    pub fn boardsize(&mut self, size: u32) -> Result<()> {
+        self.standard(StandardCommand::BoardSize(size))?.into_result().map(|_| ())
+    }
+
+    /// Clear the board.
+    pub fn clear_board(&mut self) -> Result<()> {
+        self.standard(StandardCommand::ClearBoard)?.into_result().map(|_| ())
+    }
+
+    /// Set the komi.
+    pub fn komi(&mut self, komi: f32) -> Result<()> {
+        self.standard(StandardCommand::Komi(komi))?.into_result().map(|_| ())
+    }
+
+    /// Tell the engine about a move.
+    pub fn play(&mut self, mv: Move) -> Result<()> {
+        self.standard(StandardCommand::Play(mv))?.into_result().map(|_| ())
+    }
+
+    /// Ask the engine to generate (and play) a move.
+    pub fn genmove(&mut self, color: Color) -> Result<Move> {
+        let vertex = self.standard(StandardCommand::GenMove(color))?.value::<Vertex>()?;
+        Ok(Move::new(color, vertex))
+    }
+
+    /// Ask the engine which move it would play, without playing it.
+    pub fn reg_genmove(&mut self, color: Color) -> Result<Move> {
+        let vertex = self.standard(StandardCommand::RegGenMove(color))?.value::<Vertex>()?;
+        Ok(Move::new(color, vertex))
+    }
+
+    /// Take back the last move.
+    pub fn undo(&mut self) -> Result<()> {
+        self.standard(StandardCommand::Undo)?.into_result().map(|_| ())
+    }
+
+    /// Place fixed handicap stones, returning where they went.
+    pub fn fixed_handicap(&mut self, number_of_stones: u32) -> Result<Vec<Vertex>> {
+        self.standard(StandardCommand::FixedHandicap(number_of_stones))?.values()
+    }
+
+    /// Let the engine choose where to put its handicap stones, returning
+    /// where they went.
+    pub fn place_free_handicap(&mut self, number_of_stones: u32) -> Result<Vec<Vertex>> {
+        self.standard(StandardCommand::PlaceFreeHandicap(number_of_stones))?.values()
+    }
+
+    /// Tell the engine where the handicap stones are.
+    pub fn set_free_handicap(&mut self, vertices: &[Vertex]) -> Result<()> {
+        self.standard(StandardCommand::SetFreeHandicap(vertices.to_vec()))?
+            .into_result()
+            .map(|_| ())
+    }
+
+    /// Set up the time control (see `time::TimeControl` for how the
+    /// arguments are interpreted).
+    pub fn time_settings(&mut self,
+                         main_time: u32,
+                         byo_yomi_time: u32,
+                         byo_yomi_stones: u32)
+                         -> Result<()> {
+        let command = StandardCommand::TimeSettings {
+            main_time,
+            byo_yomi_time,
+            byo_yomi_stones,
+        };
+        self.standard(command)?.into_result().map(|_| ())
+    }
+
+    /// Tell the engine how much time a player has left.
+    pub fn time_left(&mut self, color: Color, time: u32, stones: u32) -> Result<()> {
+        self.standard(StandardCommand::TimeLeft { color, time, stones })?
+            .into_result()
+            .map(|_| ())
+    }
+
+    /// Load a position from an SGF file, returning the colour to play next.
+    pub fn loadsgf(&mut self, filename: &str, move_number: Option<u32>) -> Result<Color> {
+        let command = StandardCommand::LoadSgf {
+            filename: filename.to_string(),
+            move_number,
+        };
+        self.standard(command)?.value()
+    }
+
+    /// Ask the engine to score the game.
+    pub fn final_score(&mut self) -> Result<String> {
+        self.standard(StandardCommand::FinalScore)?.into_result()
+    }
+
+    /// Ask the engine which stones have a particular status at the end of
+    /// the game.
+    pub fn final_status_list(&mut self, status: StoneStatus) -> Result<Vec<Vertex>> {
+        self.standard(StandardCommand::FinalStatusList(status))?.values()
+    }
+
+    /// Ask the engine to draw the board.
+    pub fn showboard(&mut self) -> Result<String> {
+        self.standard(StandardCommand::ShowBoard)?.into_result()
+    }
+
+    /// End the session, waiting for the engine to exit if it's a
+    /// subprocess.
+    pub fn quit(mut self) -> Result<()> {
+        self.standard(StandardCommand::Quit)?.into_result()?;
+
+        if let Some(mut child) = self.child.take() {
+            child.wait().chain_err(|| "Unable to wait for the engine to exit")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W, R> Drop for Controller<W, R> {
+    fn drop(&mut self) {
+        // make sure we don't leave a stray engine running if the controller
+        // is dropped without calling quit()
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn commands_get_incrementing_ids() {
+        let responses = Cursor::new("=1 2\n\n=2 pass\n\n?3 illegal move\n\n");
+        let mut controller = Controller::new(Vec::new(), responses);
+
+        assert_eq!(controller.protocol_version().unwrap(), 2);
+        assert_eq!(controller.genmove(Color::Black).unwrap(),
+                   Move::new(Color::Black, Vertex::Pass));
+        assert!(controller.play(Move::new(Color::White, Vertex::Pass)).is_err());
+
+        let sent = String::from_utf8(controller.writer.clone()).unwrap();
+        assert_eq!(sent, "1 protocol_version\n2 genmove black\n3 play white pass\n");
+    }
+
+    #[test]
+    fn ids_wrap_around() {
+        let responses = Cursor::new(format!("={} Fake\n\n=1 Fake\n\n", u32::MAX));
+        let mut controller = Controller::new(Vec::new(), responses);
+        controller.next_id = u32::MAX;
+
+        controller.name().unwrap();
+        controller.name().unwrap();
+
+        let sent = String::from_utf8(controller.writer.clone()).unwrap();
+        assert_eq!(sent, format!("{} name\n1 name\n", u32::MAX));
+    }
+
+    #[test]
+    fn typed_wrappers() {
+        let responses = Cursor::new("=1\n\n=2\n\n=3 C3 G7\n\n=4\n\n=5 white\n\n");
+        let mut controller = Controller::new(Vec::new(), responses);
+        let c3 = Vertex::Point { column: 2, row: 2 };
+        let g7 = Vertex::Point { column: 6, row: 6 };
+
+        controller.time_settings(600, 30, 5).unwrap();
+        controller.time_left(Color::Black, 42, 3).unwrap();
+        assert_eq!(controller.place_free_handicap(2).unwrap(), vec![c3, g7]);
+        controller.set_free_handicap(&[c3, g7]).unwrap();
+        assert_eq!(controller.loadsgf("game.sgf", Some(12)).unwrap(), Color::White);
+
+        let sent = String::from_utf8(controller.writer.clone()).unwrap();
+        assert_eq!(sent,
+                   "1 time_settings 600 30 5\n2 time_left black 42 3\n\
+                    3 place_free_handicap 2\n4 set_free_handicap C3 G7\n\
+                    5 loadsgf game.sgf 12\n");
+    }
+
+    #[test]
+    fn mismatched_ids_are_an_error() {
+        let responses = Cursor::new("=5 Fake\n\n");
+        let mut controller = Controller::new(Vec::new(), responses);
+
+        match *controller.name().unwrap_err().kind() {
+            ErrorKind::MismatchedResponse(1, Some(5)) => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+}
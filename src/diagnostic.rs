@@ -0,0 +1,239 @@
+//! Pointing at the part of a line which caused an error.
+//!
+//! A `Diagnostic` takes the line a command came from and the error it caused,
+//! works out which token was to blame, and can render the whole lot in a
+//! human-friendly format.
+//!
+//! Errors raised by the parser record their own `Span`. Errors raised later
+//! on, while interpreting a `RawCommand`'s arguments, only know the argument's
+//! index, so the line is scanned again to find out where that argument was.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use go_text_protocol::{StandardCommand, parse};
+//! use go_text_protocol::diagnostic::Diagnostic;
+//!
+//! let line = "3 play black X99";
+//! let err = parse::<StandardCommand>(line).unwrap_err();
+//!
+//! let diagnostic = Diagnostic::new(line, &err);
+//! assert_eq!(diagnostic.token(), "X99");
+//! assert_eq!(diagnostic.column(), 14);
+//!
+//! println!("{}", diagnostic);
+//! // error: Invalid argument 1 ("X99"): "X99" is not a valid vertex
+//! // --> column 14
+//! //   |
+//! //   | 3 play black X99
+//! //   |              ^^^
+//! ```
+
+use std::cmp;
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
+
+use crate::errors::*;
+use crate::parser;
+
+pub use crate::errors::Span;
+
+/// An error, along with the line which caused it and where in that line
+/// things went wrong.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diagnostic {
+    /// The (1-based) line number, if the error came from a `CommandReader`.
+    pub line_number: Option<usize>,
+    /// The offending line, without its line ending.
+    pub source: String,
+    /// Which part of `source` the error is about.
+    pub span: Span,
+    /// The error message (including its causes).
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Work out where in `line` an error came from.
+    ///
+    /// The line number is taken from any `ErrorKind::Line` in the error's
+    /// chain. If the error can't be pinned on a particular token, the span
+    /// covers the whole line.
+    pub fn new(line: &str, error: &Error) -> Diagnostic {
+        let source = line.trim_end_matches(['\r', '\n']);
+
+        let mut line_number = None;
+        let mut span = None;
+        let mut messages = Vec::new();
+
+        let mut next: Option<&(dyn StdError + 'static)> = Some(error);
+
+        while let Some(cause) = next {
+            next = cause.source();
+
+            if let Some(e) = cause.downcast_ref::<Error>() {
+                if let ErrorKind::Line(n) = *e.kind() {
+                    line_number = Some(n);
+                    continue;
+                }
+
+                if span.is_none() {
+                    span = e.span().or_else(|| locate(e.kind(), source));
+                }
+            }
+
+            messages.push(cause.to_string());
+        }
+
+        Diagnostic {
+            line_number,
+            source: source.to_string(),
+            span: span.unwrap_or_else(|| Span::new(0, source.len())),
+            message: messages.join(": "),
+        }
+    }
+
+    /// The text the error is about.
+    pub fn token(&self) -> &str {
+        &self.source[self.span.start..self.span.end]
+    }
+
+    /// The (1-based) column the span starts at, counted in characters.
+    pub fn column(&self) -> usize {
+        self.source[..self.span.start].chars().count() + 1
+    }
+}
+
+impl Display for Diagnostic {
+    /// Render the diagnostic, underlining the offending part of the line.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let label = self.line_number.map(|n| n.to_string()).unwrap_or_default();
+        let gutter = " ".repeat(label.len());
+
+        writeln!(f, "error: {}", self.message)?;
+
+        match self.line_number {
+            Some(n) => writeln!(f, "{}--> line {}, column {}", gutter, n, self.column())?,
+            None => writeln!(f, "{}--> column {}", gutter, self.column())?,
+        }
+
+        // control characters (tabs in particular) would throw the underline
+        // out, so they're drawn as spaces
+        let line: String = self.source
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        let padding = self.column() - 1;
+        let width = cmp::max(self.token().chars().count(), 1);
+
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", label, line)?;
+        write!(f, "{} | {}{}", gutter, " ".repeat(padding), "^".repeat(width))
+    }
+}
+
+/// Find the argument (or command name) an error raised while interpreting a
+/// `RawCommand` refers to.
+fn locate(kind: &ErrorKind, line: &str) -> Option<Span> {
+    let (name, args) = parser::token_spans(line)?;
+    let last = args.last().cloned().unwrap_or(name);
+
+    match *kind {
+        ErrorKind::InvalidArgument(index, _) => args.get(index).cloned(),
+        ErrorKind::MissingArgument(_) => Some(Span::new(last.end, last.end)),
+        ErrorKind::WrongArgumentCount(..) => {
+            match args.first() {
+                Some(first) => Some(Span::new(first.start, last.end)),
+                None => Some(name),
+            }
+        }
+        ErrorKind::UnknownCommand(_) => Some(name),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::StandardCommand;
+    use crate::parser::{parse, parse_borrowed};
+    use crate::reader::CommandReader;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn point_at_the_offending_token() {
+        let inputs = vec![("12play", "12play"),
+                          ("4294967296 name", "4294967296"),
+                          ("play black X99", "X99"),
+                          ("1 boardsize", "boardsize"),
+                          ("fly me to_the moon", "fly"),
+                          ("komi 6.5 7.5 # too many", "6.5 7.5"),
+                          ("genmove\tpurple\r\n", "purple")];
+
+        for (src, should_be) in inputs {
+            let err = parse::<StandardCommand>(src).unwrap_err();
+            let got = Diagnostic::new(src, &err);
+
+            assert_eq!(got.token(), should_be, "{:?} => {}", src, got);
+        }
+    }
+
+    #[test]
+    fn control_characters() {
+        let src = "pl\x07ay black D4";
+        let err = parse_borrowed(src).unwrap_err();
+
+        let got = Diagnostic::new(src, &err);
+
+        assert_eq!(got.span, Span::new(2, 3));
+        assert_eq!(got.column(), 3);
+    }
+
+    #[test]
+    fn spans_refer_to_the_original_line() {
+        let inputs = vec![("12\x07play", Span::new(0, 7)),
+                          ("\x01\x02  4294967296 name", Span::new(4, 14)),
+                          ("7  ", Span::new(1, 1)),
+                          ("pl\x07ay\x07 black\tD4 D4", Span::new(7, 18)),
+                          ("play D4 d4", Span::new(5, 7))];
+
+        for (src, should_be) in inputs {
+            let err = parse::<StandardCommand>(src).unwrap_err();
+            let got = Diagnostic::new(src, &err);
+
+            assert_eq!(got.span, should_be, "{:?} => {}", src, got);
+        }
+    }
+
+    #[test]
+    fn render_a_diagnostic() {
+        let src = "1 name\n2 play\tblack X99\n";
+        let mut reader = CommandReader::new(src.as_bytes());
+
+        let _ = reader.next().unwrap().unwrap();
+        let raw = reader.next().unwrap().unwrap();
+        let err = StandardCommand::try_from(raw)
+            .chain_err(|| ErrorKind::Line(reader.line_number()))
+            .unwrap_err();
+
+        let got = Diagnostic::new(&reader.last_line(), &err);
+        let should_be = "error: Invalid argument 1 (\"X99\"): \"X99\" is not a valid vertex
+ --> line 2, column 14
+  |
+2 | 2 play black X99
+  |              ^^^";
+
+        assert_eq!(got.line_number, Some(2));
+        assert_eq!(got.to_string(), should_be);
+    }
+
+    #[test]
+    fn render_without_a_line_number() {
+        let src = "quit now";
+        let err = parse::<StandardCommand>(src).unwrap_err();
+
+        let got = Diagnostic::new(src, &err).to_string();
+
+        assert!(got.ends_with("--> column 6\n |\n | quit now\n |      ^^^"), "{}", got);
+    }
+}
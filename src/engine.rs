@@ -0,0 +1,579 @@
+//! Machinery for writing a GTP engine.
+//!
+//! Implement the `Engine` trait for your engine, then hand it to
+//! `run_session()` along with the streams to talk over (usually `stdin` and
+//! `stdout`). The session loop takes care of parsing commands, echoing ids,
+//! formatting responses, and the purely administrative commands
+//! (`protocol_version`, `name`, `version`, `known_command`, `list_commands`,
+//! and `quit`).
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::io::Cursor;
+//! use go_text_protocol::{Color, Move, Vertex};
+//! use go_text_protocol::engine::{Engine, CommandResult, run_session};
+//!
+//! struct AlwaysPass;
+//!
+//! impl Engine for AlwaysPass {
+//!     fn name(&self) -> String { "AlwaysPass".to_string() }
+//!     fn version(&self) -> String { "1.0".to_string() }
+//!
+//!     fn boardsize(&mut self, _size: u32) -> CommandResult<()> { Ok(()) }
+//!     fn clear_board(&mut self) -> CommandResult<()> { Ok(()) }
+//!     fn komi(&mut self, _komi: f32) -> CommandResult<()> { Ok(()) }
+//!     fn play(&mut self, _mv: Move) -> CommandResult<()> { Ok(()) }
+//!     fn genmove(&mut self, _color: Color) -> CommandResult<Vertex> { Ok(Vertex::Pass) }
+//! }
+//!
+//! let input = Cursor::new("1 name\n2 genmove b\nquit\n");
+//! let mut output = Vec::new();
+//!
+//! run_session(input, &mut output, &mut AlwaysPass).unwrap();
+//!
+//! assert_eq!(String::from_utf8(output).unwrap(), "=1 AlwaysPass\n\n=2 pass\n\n=\n\n");
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::io::{BufRead, Write};
+
+use crate::commands::StandardCommand;
+use crate::errors::*;
+use crate::parser::{Parser, RawCommand, preprocess, split_id};
+use crate::reader::CommandReader;
+use crate::response::Response;
+use crate::values::{Color, Move, StoneStatus, Vertex};
+
+/// The result of an engine carrying out a command. The error is the failure
+/// message sent back to the controller (e.g. `illegal move`).
+pub type CommandResult<T> = ::std::result::Result<T, String>;
+
+/// The failure message used for commands an engine doesn't implement.
+pub const UNKNOWN_COMMAND: &str = "unknown command";
+
+/// The failure message used when a command's arguments are wrong.
+pub const SYNTAX_ERROR: &str = "syntax error";
+
+/// The failure message used when a move breaks the rules.
+pub const ILLEGAL_MOVE: &str = "illegal move";
+
+/// The failure message used when there's no move to undo.
+pub const CANNOT_UNDO: &str = "cannot undo";
+
+/// The failure message used when handicap stones are placed on a board
+/// which already has stones on it.
+pub const BOARD_NOT_EMPTY: &str = "board not empty";
+
+/// The failure message used when asking for too many (or too few) handicap
+/// stones.
+pub const INVALID_NUMBER_OF_STONES: &str = "invalid number of stones";
+
+/// The failure message used when `set_free_handicap` is given vertices which
+/// are repeated, off the board, or too few or too many.
+pub const BAD_VERTEX_LIST: &str = "bad vertex list";
+
+/// The failure message used when `boardsize` asks for a size the engine
+/// can't handle.
+pub const UNACCEPTABLE_SIZE: &str = "unacceptable size";
+
+/// The failure message used when `loadsgf` can't read or understand a file.
+pub const CANNOT_LOAD_FILE: &str = "cannot load file";
+
+/// The failure message used when `printsgf` can't write to a file.
+pub const CANNOT_SAVE_FILE: &str = "cannot save file";
+
+/// The commands every engine must support.
+pub const REQUIRED_COMMANDS: &[&str] = &["protocol_version",
+                                         "name",
+                                         "version",
+                                         "known_command",
+                                         "list_commands",
+                                         "quit",
+                                         "boardsize",
+                                         "clear_board",
+                                         "komi",
+                                         "play",
+                                         "genmove"];
+
+fn unknown<T>() -> CommandResult<T> {
+    Err(UNKNOWN_COMMAND.to_string())
+}
+
+/// A Go engine which can be driven over GTP.
+///
+/// The methods for required commands must be implemented, while the optional
+/// ones default to failing with `unknown command`. If you implement any
+/// optional (or custom) commands, make sure to also list them in
+/// `optional_commands()` so they are reported by `list_commands` and
+/// `known_command`.
+pub trait Engine {
+    /// The engine's name, as reported by `name`.
+    fn name(&self) -> String;
+
+    /// The engine's version, as reported by `version`.
+    fn version(&self) -> String;
+
+    /// Change the board size, failing with `unacceptable size` if it isn't
+    /// supported.
+    fn boardsize(&mut self, size: u32) -> CommandResult<()>;
+
+    /// Clear the board, captured stones, and move history.
+    fn clear_board(&mut self) -> CommandResult<()>;
+
+    /// Set the komi.
+    fn komi(&mut self, komi: f32) -> CommandResult<()>;
+
+    /// Play a stone of the given colour, failing with `illegal move` if it
+    /// isn't allowed.
+    fn play(&mut self, mv: Move) -> CommandResult<()>;
+
+    /// Generate and play a move for the given colour.
+    fn genmove(&mut self, color: Color) -> CommandResult<Vertex>;
+
+    /// The names of any optional or custom commands this engine supports.
+    fn optional_commands(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Place handicap stones at the fixed vertices defined by the spec.
+    fn fixed_handicap(&mut self, _number_of_stones: u32) -> CommandResult<Vec<Vertex>> {
+        unknown()
+    }
+
+    /// Place handicap stones wherever the engine likes.
+    fn place_free_handicap(&mut self, _number_of_stones: u32) -> CommandResult<Vec<Vertex>> {
+        unknown()
+    }
+
+    /// Place handicap stones at the provided vertices.
+    fn set_free_handicap(&mut self, _vertices: &[Vertex]) -> CommandResult<()> {
+        unknown()
+    }
+
+    /// Take back the last move, failing with `cannot undo` if that isn't
+    /// possible.
+    fn undo(&mut self) -> CommandResult<()> {
+        unknown()
+    }
+
+    /// Set up the time control.
+    fn time_settings(&mut self,
+                     _main_time: u32,
+                     _byo_yomi_time: u32,
+                     _byo_yomi_stones: u32)
+                     -> CommandResult<()> {
+        unknown()
+    }
+
+    /// Update a player's clock.
+    fn time_left(&mut self, _color: Color, _time: u32, _stones: u32) -> CommandResult<()> {
+        unknown()
+    }
+
+    /// Score the game (e.g. `B+3.5`, `W+R`, or `0`).
+    fn final_score(&mut self) -> CommandResult<String> {
+        unknown()
+    }
+
+    /// Get the stones with a particular status at the end of the game.
+    fn final_status_list(&mut self, _status: StoneStatus) -> CommandResult<Vec<Vertex>> {
+        unknown()
+    }
+
+    /// Load a position from an SGF file, returning the colour to play next.
+    fn loadsgf(&mut self, _filename: &str, _move_number: Option<u32>) -> CommandResult<Color> {
+        unknown()
+    }
+
+    /// Generate a move without playing it.
+    fn reg_genmove(&mut self, _color: Color) -> CommandResult<Vertex> {
+        unknown()
+    }
+
+    /// Draw the board.
+    fn showboard(&mut self) -> CommandResult<String> {
+        unknown()
+    }
+
+    /// Handle a command which isn't part of the GTP v2 spec.
+    fn custom_command(&mut self, _command: &RawCommand) -> CommandResult<String> {
+        unknown()
+    }
+}
+
+/// Extra knobs for tweaking how `run_session_with()` behaves.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct SessionOptions {
+    /// Treat command ids as arbitrary digit strings and echo them back
+    /// exactly as they were written (e.g. `007` or `99999999999`), instead
+    /// of parsing them as a `u32`.
+    pub verbatim_ids: bool,
+}
+
+/// Run a GTP session, reading commands from `reader` and writing responses
+/// to `writer` until the controller sends `quit` or closes the stream.
+pub fn run_session<R, W, E>(reader: R, writer: W, engine: &mut E) -> Result<()>
+    where R: BufRead,
+          W: Write,
+          E: Engine + ?Sized
+{
+    run_session_with(reader, writer, engine, SessionOptions::default())
+}
+
+/// Run a GTP session, the same as `run_session()`, but with some extra
+/// options.
+///
+/// If a line can't be parsed the engine replies `? syntax error`, echoing
+/// the line's id as written (even if it's too big to be a valid id) so the
+/// controller can still match the failure up with its command.
+pub fn run_session_with<R, W, E>(reader: R,
+                                 mut writer: W,
+                                 engine: &mut E,
+                                 options: SessionOptions)
+                                 -> Result<()>
+    where R: BufRead,
+          W: Write,
+          E: Engine + ?Sized
+{
+    let mut commands = CommandReader::new(reader);
+
+    while let Some(cmd) = commands.next() {
+        let cmd = match cmd {
+            Err(e) if *e.kind() == ErrorKind::Io => return Err(e),
+            other => other,
+        };
+
+        let (response, quit) = respond_to_line(&commands.last_line(), cmd, engine, options);
+        writer.write_all(response.as_bytes())?;
+        writer.flush()?;
+
+        if quit {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Work out the reply to a line the controller sent (and which has already
+/// been parsed as `cmd`), returning it in wire format along with whether the
+/// session should end.
+///
+/// This is shared by `run_session_with()` and its async equivalent so both
+/// answer a line exactly the same way.
+pub(crate) fn respond_to_line<E>(line: &str,
+                                 cmd: Result<RawCommand>,
+                                 engine: &mut E,
+                                 options: SessionOptions)
+                                 -> (String, bool)
+    where E: Engine + ?Sized
+{
+    let line = preprocess(line).unwrap_or_default();
+    let (id, rest) = split_id(&line);
+
+    // the line will have been parsed with the id as a u32, so verbatim ids
+    // mean parsing it again without it (unless it was cut short)
+    let cmd = match cmd {
+        Err(e) if matches!(e.kind(), ErrorKind::LineTooLong(_)) => Err(e),
+        _ if options.verbatim_ids => Parser::new(rest).parse(),
+        cmd => cmd,
+    };
+
+    match cmd {
+        Ok(raw) => {
+            let response = handle_command(&raw, engine);
+            let quit = raw.name.eq_ignore_ascii_case("quit");
+
+            if options.verbatim_ids {
+                (format_with_id(&response, id), quit)
+            } else {
+                (response.to_string(), quit)
+            }
+        }
+        Err(_) => (format_with_id(&Response::failure(None, SYNTAX_ERROR), id), false),
+    }
+}
+
+/// Format a response without an id of its own, using `id` exactly as it was
+/// written in the command instead.
+fn format_with_id(response: &Response, id: Option<&str>) -> String {
+    let formatted = response.to_string();
+    let (status, rest) = formatted.split_at(1);
+
+    format!("{}{}{}", status, id.unwrap_or(""), rest)
+}
+
+/// Carry out a single command, returning the response to send back.
+pub fn handle_command<E>(raw: &RawCommand, engine: &mut E) -> Response
+    where E: Engine + ?Sized
+{
+    // command names aren't case sensitive
+    let result = match raw.name.to_ascii_lowercase().as_str() {
+        "protocol_version" => Ok("2".to_string()),
+        "name" => Ok(engine.name()),
+        "version" => Ok(engine.version()),
+        "quit" => Ok(String::new()),
+        "known_command" => {
+            let known = raw.args.len() == 1 && is_known(engine, &raw.args[0]);
+            Ok(known.to_string())
+        }
+        "list_commands" => Ok(known_commands(engine).join("\n")),
+        _ => dispatch(raw, engine),
+    };
+
+    Response::reply_to(raw, result)
+}
+
+fn known_commands<E>(engine: &E) -> Vec<String>
+    where E: Engine + ?Sized
+{
+    let mut names: Vec<String> = REQUIRED_COMMANDS.iter().map(|s| s.to_string()).collect();
+
+    for name in engine.optional_commands() {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+fn is_known<E>(engine: &E, name: &str) -> bool
+    where E: Engine + ?Sized
+{
+    known_commands(engine).iter().any(|known| known.eq_ignore_ascii_case(name))
+}
+
+fn dispatch<E>(raw: &RawCommand, engine: &mut E) -> CommandResult<String>
+    where E: Engine + ?Sized
+{
+    let cmd = match StandardCommand::try_from(raw) {
+        Ok(cmd) => cmd,
+        Err(ref e) if matches!(*e.kind(), ErrorKind::UnknownCommand(_)) => {
+            return engine.custom_command(raw);
+        }
+        Err(_) => return Err(SYNTAX_ERROR.to_string()),
+    };
+
+    match cmd {
+        StandardCommand::BoardSize(size) => engine.boardsize(size).map(empty),
+        StandardCommand::ClearBoard => engine.clear_board().map(empty),
+        StandardCommand::Komi(komi) => engine.komi(komi).map(empty),
+        StandardCommand::Play(mv) => engine.play(mv).map(empty),
+        StandardCommand::GenMove(color) => engine.genmove(color).map(|v| v.to_string()),
+        StandardCommand::FixedHandicap(n) => engine.fixed_handicap(n).map(|v| join(&v)),
+        StandardCommand::PlaceFreeHandicap(n) => engine.place_free_handicap(n).map(|v| join(&v)),
+        StandardCommand::SetFreeHandicap(ref vertices) => {
+            engine.set_free_handicap(vertices).map(empty)
+        }
+        StandardCommand::Undo => engine.undo().map(empty),
+        StandardCommand::TimeSettings { main_time, byo_yomi_time, byo_yomi_stones } => {
+            engine.time_settings(main_time, byo_yomi_time, byo_yomi_stones).map(empty)
+        }
+        StandardCommand::TimeLeft { color, time, stones } => {
+            engine.time_left(color, time, stones).map(empty)
+        }
+        StandardCommand::FinalScore => engine.final_score(),
+        StandardCommand::FinalStatusList(status) => {
+            engine.final_status_list(status).map(|v| join(&v))
+        }
+        StandardCommand::LoadSgf { ref filename, move_number } => {
+            engine.loadsgf(filename, move_number).map(|c| c.to_string())
+        }
+        StandardCommand::RegGenMove(color) => engine.reg_genmove(color).map(|v| v.to_string()),
+        StandardCommand::ShowBoard => engine.showboard(),
+        // the administrative commands are all handled by handle_command()
+        _ => unknown(),
+    }
+}
+
+fn empty(_: ()) -> String {
+    String::new()
+}
+
+pub(crate) fn join<T: Display>(items: &[T]) -> String {
+    items.iter().map(|item| item.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[derive(Default)]
+    struct DummyEngine {
+        size: u32,
+        komi: f32,
+        moves: Vec<Move>,
+    }
+
+    impl Engine for DummyEngine {
+        fn name(&self) -> String {
+            "Dummy".to_string()
+        }
+
+        fn version(&self) -> String {
+            "0.1.0".to_string()
+        }
+
+        fn boardsize(&mut self, size: u32) -> CommandResult<()> {
+            if size > 19 {
+                Err("unacceptable size".to_string())
+            } else {
+                self.size = size;
+                Ok(())
+            }
+        }
+
+        fn clear_board(&mut self) -> CommandResult<()> {
+            self.moves.clear();
+            Ok(())
+        }
+
+        fn komi(&mut self, komi: f32) -> CommandResult<()> {
+            self.komi = komi;
+            Ok(())
+        }
+
+        fn play(&mut self, mv: Move) -> CommandResult<()> {
+            if self.moves.iter().any(|m| m.vertex == mv.vertex && mv.vertex != Vertex::Pass) {
+                return Err("illegal move".to_string());
+            }
+            self.moves.push(mv);
+            Ok(())
+        }
+
+        fn genmove(&mut self, color: Color) -> CommandResult<Vertex> {
+            self.moves.push(Move::new(color, Vertex::Pass));
+            Ok(Vertex::Pass)
+        }
+
+        fn optional_commands(&self) -> Vec<String> {
+            vec!["undo".to_string(), "echo".to_string()]
+        }
+
+        fn undo(&mut self) -> CommandResult<()> {
+            self.moves.pop().map(|_| ()).ok_or_else(|| "cannot undo".to_string())
+        }
+
+        fn custom_command(&mut self, command: &RawCommand) -> CommandResult<String> {
+            match command.name.as_str() {
+                "echo" => Ok(command.args.join(" ")),
+                _ => unknown(),
+            }
+        }
+    }
+
+    fn run_with(input: &str, options: SessionOptions) -> String {
+        let mut output = Vec::new();
+        run_session_with(Cursor::new(input), &mut output, &mut DummyEngine::default(), options)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn run(input: &str, engine: &mut DummyEngine) -> String {
+        let mut output = Vec::new();
+        run_session(Cursor::new(input), &mut output, engine).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn administrative_commands() {
+        let input = "1 protocol_version\n2 name\n3 version\n\
+                     4 known_command undo\n5 known_command showboard\n";
+        let should_be = "=1 2\n\n=2 Dummy\n\n=3 0.1.0\n\n=4 true\n\n=5 false\n\n";
+
+        let got = run(input, &mut DummyEngine::default());
+
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn list_commands() {
+        let got = run("list_commands", &mut DummyEngine::default());
+
+        let mut should_be = String::from("= ");
+        should_be.push_str(&REQUIRED_COMMANDS.join("\n"));
+        should_be.push_str("\nundo\necho\n\n");
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn play_a_game() {
+        let mut engine = DummyEngine::default();
+        let input = "boardsize 9\nkomi 5.5\n# a comment\n\n10 play b E5\n11 play w E5\n\
+                     12 genmove white\nundo\nundo\n13 undo\n";
+        let should_be = "=\n\n=\n\n=10\n\n?11 illegal move\n\n=12 pass\n\n=\n\n=\n\n\
+                         ?13 cannot undo\n\n";
+
+        let got = run(input, &mut engine);
+
+        assert_eq!(got, should_be);
+        assert_eq!(engine.size, 9);
+        assert_eq!(engine.komi, 5.5);
+    }
+
+    #[test]
+    fn failures() {
+        let input = "boardsize 25\nplay black\n3 showboard\n4 echo hi there\n5 foo\n";
+        let should_be = "? unacceptable size\n\n? syntax error\n\n?3 unknown command\n\n\
+                         =4 hi there\n\n?5 unknown command\n\n";
+
+        let got = run(input, &mut DummyEngine::default());
+
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn quit_ends_the_session() {
+        let got = run("7 quit\nname\n", &mut DummyEngine::default());
+
+        assert_eq!(got, "=7\n\n");
+    }
+
+    #[test]
+    fn command_names_are_not_case_sensitive() {
+        let input = "1 PROTOCOL_VERSION\n2 Known_Command UNDO\n3 QUIT\nname\n";
+        let should_be = "=1 2\n\n=2 true\n\n=3\n\n";
+
+        let got = run(input, &mut DummyEngine::default());
+
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn syntax_errors_echo_the_id() {
+        let input = "4294967296 name\n7\n8 name\n";
+        let should_be = "?4294967296 syntax error\n\n?7 syntax error\n\n=8 Dummy\n\n";
+
+        let got = run(input, &mut DummyEngine::default());
+
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn verbatim_ids() {
+        let options = SessionOptions { verbatim_ids: true };
+        let input = "007 name\n99999999999 protocol_version\n12 play black\nversion\n";
+        let should_be = "=007 Dummy\n\n=99999999999 2\n\n?12 syntax error\n\n= 0.1.0\n\n";
+
+        let got = run_with(input, options);
+
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn overlong_lines_are_syntax_errors() {
+        for &verbatim_ids in &[false, true] {
+            let options = SessionOptions { verbatim_ids };
+            let cmd = Err(ErrorKind::LineTooLong(10).into());
+
+            let got = respond_to_line("12 play bl", cmd, &mut DummyEngine::default(), options);
+
+            assert_eq!(got, ("?12 syntax error\n\n".to_string(), false));
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! The crate's error type.
+//!
+//! Every fallible operation returns an `Error`, which is an `ErrorKind` plus
+//! (optionally) the lower level error which caused it, available through
+//! `std::error::Error::source()`.
+//!
+//! ```rust
+//! use go_text_protocol::{ErrorKind, RawCommand, Vertex, parse};
+//!
+//! let cmd: RawCommand = parse("play black Z42").unwrap();
+//! let err = cmd.arg::<Vertex>(1).unwrap_err();
+//!
+//! match *err.kind() {
+//!     ErrorKind::InvalidArgument(1, ref arg) => assert_eq!(arg, "Z42"),
+//!     ref other => panic!("Unexpected error: {:?}", other),
+//! }
+//!
+//! // and the reason it was invalid is available as the error's source
+//! let source = std::error::Error::source(&err).unwrap();
+//! assert_eq!(source.to_string(), "\"Z42\" is not a valid vertex");
+//! ```
+
+use std::convert::Infallible;
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+/// A specialised `Result` type for this crate.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// The different kinds of error which can happen.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ErrorKind {
+    /// A free-form error message.
+    Msg(String),
+
+    /// Reading or writing failed. The underlying `std::io::Error` is the
+    /// error's source.
+    Io,
+
+    /// Whitespace was expected.
+    NoWhitespace,
+
+    /// The string doesn't contain a command.
+    NoCommand,
+
+    /// The line was empty (or only contained whitespace and comments) after
+    /// preprocessing, and should be ignored.
+    EmptyLine,
+
+
+    /// The command's id is too big to fit in a `u32`.
+    IdOverflow(String),
+
+    /// The line contains control characters which would need to be removed
+    /// before it can be parsed without copying.
+    ControlCharacter(usize),
+
+    /// A line in a stream of commands couldn't be parsed.
+    Line(usize),
+    /// A line was longer than the maximum length allowed (in bytes).
+    LineTooLong(usize),
+
+    /// A string couldn't be parsed as one of the GTP value types (the type
+    /// name and the offending value).
+    InvalidValue(&'static str, String),
+
+    /// The command didn't have an argument at the requested position.
+    MissingArgument(usize),
+
+    /// A command argument couldn't be parsed as the requested type (the
+    /// argument's index and its value).
+    InvalidArgument(usize, String),
+
+    /// A command was given the wrong number of arguments (the command, how
+    /// many it expected, and how many it got).
+    WrongArgumentCount(String, String, usize),
+
+    /// The command isn't one we know about.
+    UnknownCommand(String),
+
+    /// A response from the engine couldn't be parsed.
+    MalformedResponse(String),
+
+    /// An SGF file couldn't be parsed.
+    MalformedSgf(String),
+
+    /// The stream ended before a complete response was received.
+    IncompleteResponse,
+
+    /// The engine responded to a different command than the one we were
+    /// expecting.
+    MismatchedResponse(u32, Option<u32>),
+
+    /// The engine responded with a failure (`?`).
+    ResponseFailure(String),
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::Msg(ref msg) => write!(f, "{}", msg),
+            ErrorKind::Io => write!(f, "An I/O error occurred"),
+            ErrorKind::NoWhitespace => write!(f, "Expected whitespace"),
+            ErrorKind::NoCommand => write!(f, "No command was given"),
+            ErrorKind::EmptyLine => write!(f, "The line is empty"),
+            ErrorKind::IdOverflow(ref id) => write!(f, "The id {} is too big", id),
+            ErrorKind::ControlCharacter(position) => {
+                write!(f, "Unexpected control character at byte {}", position)
+            }
+            ErrorKind::Line(line_number) => write!(f, "Unable to parse line {}", line_number),
+            ErrorKind::LineTooLong(max) => {
+                write!(f, "The line is longer than the maximum of {} bytes", max)
+            }
+            ErrorKind::InvalidValue(type_name, ref value) => {
+                write!(f, "\"{}\" is not a valid {}", value, type_name)
+            }
+            ErrorKind::MissingArgument(index) => write!(f, "Missing argument {}", index),
+            ErrorKind::InvalidArgument(index, ref arg) => {
+                write!(f, "Invalid argument {} ({:?})", index, arg)
+            }
+            ErrorKind::WrongArgumentCount(ref command, ref expected, got) => {
+                write!(f, "\"{}\" expects {} argument(s) but got {}", command, expected, got)
+            }
+            ErrorKind::UnknownCommand(ref name) => write!(f, "Unknown command: {}", name),
+            ErrorKind::MalformedResponse(ref line) => write!(f, "Malformed response: {:?}", line),
+            ErrorKind::MalformedSgf(ref reason) => write!(f, "Malformed SGF: {}", reason),
+            ErrorKind::IncompleteResponse => {
+                write!(f, "The stream ended before a complete response was received")
+            }
+            ErrorKind::MismatchedResponse(expected, got) => {
+                write!(f, "Expected a response to command {} but got {:?}", expected, got)
+            }
+            ErrorKind::ResponseFailure(ref message) => {
+                write!(f, "The engine reported a failure: {}", message)
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a str> for ErrorKind {
+    fn from(msg: &'a str) -> ErrorKind {
+        ErrorKind::Msg(msg.to_string())
+    }
+}
+
+impl From<String> for ErrorKind {
+    fn from(msg: String) -> ErrorKind {
+        ErrorKind::Msg(msg)
+    }
+}
+
+/// A range of bytes within a line.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    /// The index of the first byte.
+    pub start: usize,
+    /// One past the index of the last byte.
+    pub end: usize,
+}
+
+impl Span {
+    /// Create a new `Span`.
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    /// Is this span zero bytes long?
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+}
+
+/// The error type used throughout this crate.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    span: Option<Span>,
+    source: Option<Box<dyn StdError + Send + Sync + 'static>>,
+}
+
+impl Error {
+    /// Create an error of a particular kind.
+    pub fn new<K: Into<ErrorKind>>(kind: K) -> Error {
+        Error {
+            kind: kind.into(),
+            span: None,
+            source: None,
+        }
+    }
+
+    /// Create an error of a particular kind which was caused by another
+    /// error.
+    pub fn with_source<K, E>(kind: K, source: E) -> Error
+        where K: Into<ErrorKind>,
+              E: Into<Box<dyn StdError + Send + Sync + 'static>>
+    {
+        Error {
+            kind: kind.into(),
+            span: None,
+            source: Some(source.into()),
+        }
+    }
+
+    /// Record which part of the line being parsed caused this error.
+    pub fn with_span(mut self, span: Span) -> Error {
+        self.span = Some(span);
+        self
+    }
+
+    /// What kind of error is this?
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// The part of the offending line this error is about, if the parser
+    /// knew where it went wrong.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Throw away the error's source, keeping only its kind.
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.source {
+            Some(ref source) => Some(&**source),
+            None => None,
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error::new(kind)
+    }
+}
+
+impl<'a> From<&'a str> for Error {
+    fn from(msg: &'a str) -> Error {
+        Error::new(msg)
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Error {
+        Error::new(msg)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::with_source(ErrorKind::Io, e)
+    }
+}
+
+impl From<Infallible> for Error {
+    fn from(never: Infallible) -> Error {
+        match never {}
+    }
+}
+
+/// Adds context to the error in a `Result`.
+pub(crate) trait ResultExt<T> {
+    /// If this is an error, wrap it in a new error of the kind returned by
+    /// `kind`.
+    fn chain_err<F, K>(self, kind: F) -> Result<T>
+        where F: FnOnce() -> K,
+              K: Into<ErrorKind>;
+}
+
+impl<T, E> ResultExt<T> for ::std::result::Result<T, E>
+    where E: Into<Box<dyn StdError + Send + Sync + 'static>>
+{
+    fn chain_err<F, K>(self, kind: F) -> Result<T>
+        where F: FnOnce() -> K,
+              K: Into<ErrorKind>
+    {
+        self.map_err(|e| Error::with_source(kind(), e))
+    }
+}
+
+/// Return early with an error.
+macro_rules! bail {
+    ($kind:expr) => {
+        return Err($crate::errors::Error::from($kind))
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_can_be_chained() {
+        let io_error = io::Error::new(io::ErrorKind::UnexpectedEof, "oops");
+        let err = Err::<(), _>(Error::from(io_error))
+            .chain_err(|| ErrorKind::Line(7))
+            .unwrap_err();
+
+        assert_eq!(*err.kind(), ErrorKind::Line(7));
+
+        let io_error = err.source().unwrap().downcast_ref::<Error>().unwrap();
+        assert_eq!(*io_error.kind(), ErrorKind::Io);
+        assert_eq!(io_error.source().unwrap().to_string(), "oops");
+    }
+
+    #[test]
+    fn errors_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+        assert_send_sync::<Error>();
+    }
+}
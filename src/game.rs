@@ -0,0 +1,465 @@
+//! Keeping track of a whole game, not just the current position.
+//!
+//! A `Game` wraps a `Board` with the things GTP commands need to know about
+//! the game as a whole: the komi, handicap stones, and the moves played so
+//! far (so they can be undone).
+//!
+//! # Examples
+//!
+//! ```rust
+//! use go_text_protocol::{RawCommand, parse};
+//! use go_text_protocol::game::Game;
+//!
+//! let mut game = Game::default();
+//!
+//! for line in &["boardsize 9", "komi 5.5", "play black E5", "play white C3", "undo"] {
+//!     let cmd: RawCommand = parse(line).unwrap();
+//!     game.execute_raw(&cmd).unwrap().unwrap();
+//! }
+//!
+//! assert_eq!(game.size(), 9);
+//! assert_eq!(game.komi(), 5.5);
+//! assert_eq!(game.moves().len(), 1);
+//!
+//! // there's only one move left to take back
+//! let cmd: RawCommand = parse("gg-undo 2").unwrap();
+//! assert_eq!(game.execute_raw(&cmd), Some(Err("cannot undo".to_string())));
+//! ```
+
+use std::convert::TryFrom;
+use std::fs;
+
+use crate::board::Board;
+use crate::commands::StandardCommand;
+use crate::engine::{self, BOARD_NOT_EMPTY, CANNOT_LOAD_FILE, CANNOT_SAVE_FILE, CANNOT_UNDO,
+                    CommandResult, SYNTAX_ERROR};
+use crate::errors::ErrorKind;
+use crate::parser::RawCommand;
+use crate::scoring::{GameResult, Rules, Scorer};
+use crate::sgf::GameRecord;
+use crate::values::{Color, Move, Vertex};
+
+/// The board size used by `Game::default()`.
+pub const DEFAULT_BOARD_SIZE: u32 = 19;
+
+/// A game of Go, with its history.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Game {
+    komi: f32,
+    handicap: Vec<Vertex>,
+    /// The position after any handicap stones were placed.
+    start: Board,
+    moves: Vec<Move>,
+    board: Board,
+}
+
+impl Game {
+    /// Start a new game on an empty board, failing with `unacceptable size`
+    /// if the size isn't supported. The komi starts at `0`.
+    pub fn new(size: u32) -> CommandResult<Game> {
+        let board = Board::new(size)?;
+
+        Ok(Game {
+            komi: 0.0,
+            handicap: Vec::new(),
+            start: board.clone(),
+            moves: Vec::new(),
+            board,
+        })
+    }
+
+    /// The current position.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The board size.
+    pub fn size(&self) -> u32 {
+        self.board.size()
+    }
+
+    /// The komi.
+    pub fn komi(&self) -> f32 {
+        self.komi
+    }
+
+    /// Change the komi.
+    pub fn set_komi(&mut self, komi: f32) {
+        self.komi = komi;
+    }
+
+    /// Where any handicap stones were placed.
+    pub fn handicap(&self) -> &[Vertex] {
+        &self.handicap
+    }
+
+    /// Every move played so far (not including handicap stones).
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Whose turn is it? Black goes first, unless there's a handicap.
+    pub fn to_play(&self) -> Color {
+        match self.moves.last() {
+            Some(mv) => mv.color.opponent(),
+            None if self.handicap.is_empty() => Color::Black,
+            None => Color::White,
+        }
+    }
+
+    /// Change the board size, which also clears the board.
+    pub fn boardsize(&mut self, size: u32) -> CommandResult<()> {
+        let komi = self.komi;
+        *self = Game::new(size)?;
+        self.komi = komi;
+        Ok(())
+    }
+
+    /// Clear the board, handicap, and move history (but not the komi).
+    pub fn clear_board(&mut self) {
+        self.board.clear();
+        self.start = self.board.clone();
+        self.handicap.clear();
+        self.moves.clear();
+    }
+
+    /// Play a move, failing with `illegal move` if it isn't allowed.
+    pub fn play(&mut self, mv: Move) -> CommandResult<()> {
+        self.board.play(mv)?;
+        self.moves.push(mv);
+        Ok(())
+    }
+
+    /// Take back the last move, failing with `cannot undo` if there aren't
+    /// any.
+    pub fn undo(&mut self) -> CommandResult<()> {
+        self.undo_moves(1)
+    }
+
+    /// Take back the last `count` moves (GNU Go's `gg-undo`), failing with
+    /// `cannot undo` if there aren't that many.
+    pub fn undo_moves(&mut self, count: usize) -> CommandResult<()> {
+        if count > self.moves.len() {
+            return Err(CANNOT_UNDO.to_string());
+        }
+
+        let move_number = self.moves.len() - count;
+        self.board = self.board_at(move_number).expect("We checked the move number");
+        self.moves.truncate(move_number);
+        Ok(())
+    }
+
+    /// Replay the game from the start, getting the position after the first
+    /// `move_number` moves (or `None` if that many haven't been played).
+    pub fn board_at(&self, move_number: usize) -> Option<Board> {
+        let moves = self.moves.get(..move_number)?;
+        let mut board = self.start.clone();
+
+        for &mv in moves {
+            board.play(mv).expect("Moves in the history are always legal");
+        }
+
+        Some(board)
+    }
+
+    /// Score the current position using the game's komi, with the groups at
+    /// each of the `dead` vertices removed.
+    pub fn score(&self, dead: &[Vertex], rules: Rules) -> GameResult {
+        let mut scorer = Scorer::new(&self.board);
+
+        for &vertex in dead {
+            scorer.mark_dead(vertex);
+        }
+
+        scorer.score(self.komi, rules)
+    }
+
+    /// Place fixed handicap stones, returning where they went.
+    pub fn fixed_handicap(&mut self, stones: u32) -> CommandResult<Vec<Vertex>> {
+        self.check_no_moves()?;
+        let vertices = self.board.fixed_handicap(stones)?;
+        self.set_handicap(&vertices);
+        Ok(vertices)
+    }
+
+    /// Place free handicap stones wherever we like, returning where they
+    /// went.
+    ///
+    /// Section 4.1.2 of the spec lets an engine place fewer stones than were
+    /// asked for, and this never places more than the fixed handicap points
+    /// (so at most 9, or 2 on boards too small for fixed handicaps). The
+    /// controller can tell by counting the vertices in the result.
+    pub fn place_free_handicap(&mut self, stones: u32) -> CommandResult<Vec<Vertex>> {
+        self.check_no_moves()?;
+        let vertices = self.board.place_free_handicap(stones)?;
+        self.set_handicap(&vertices);
+        Ok(vertices)
+    }
+
+    /// Place free handicap stones on the provided vertices.
+    pub fn set_free_handicap(&mut self, vertices: &[Vertex]) -> CommandResult<()> {
+        self.check_no_moves()?;
+        self.board.set_free_handicap(vertices)?;
+        self.set_handicap(vertices);
+        Ok(())
+    }
+
+    /// Put stones on the board before the game starts (e.g. an SGF file's
+    /// `AB` and `AW` setup), failing with `board not empty` if any moves
+    /// have been played.
+    pub fn setup(&mut self, color: Color, vertices: &[Vertex]) -> CommandResult<()> {
+        self.check_no_moves()?;
+        self.board.add_stones(color, vertices)?;
+        self.start = self.board.clone();
+        Ok(())
+    }
+
+    /// Replace this game with the one in an SGF file, playing every move
+    /// before `move_number` (or all of them), and return whose turn it is.
+    ///
+    /// Fails with `cannot load file` if the file can't be read, isn't valid
+    /// SGF, or contains an illegal position. The game is left untouched when
+    /// that happens.
+    pub fn loadsgf(&mut self, filename: &str, move_number: Option<u32>) -> CommandResult<Color> {
+        let game = fs::read_to_string(filename)
+            .ok()
+            .and_then(|src| src.parse::<GameRecord>().ok())
+            .and_then(|record| record.to_game(move_number).ok())
+            .ok_or_else(|| CANNOT_LOAD_FILE.to_string())?;
+
+        *self = game;
+        Ok(self.to_play())
+    }
+
+    /// The game as an SGF file.
+    pub fn to_sgf(&self) -> String {
+        GameRecord::from(self).to_string()
+    }
+
+    /// GNU Go's `printsgf`, which saves the game to `filename` or, if no file
+    /// is given, returns the SGF as the response.
+    ///
+    /// Fails with `cannot save file` if the file can't be written.
+    pub fn printsgf(&self, filename: Option<&str>) -> CommandResult<String> {
+        match filename {
+            Some(filename) => {
+                fs::write(filename, self.to_sgf()).map_err(|_| CANNOT_SAVE_FILE.to_string())?;
+                Ok(String::new())
+            }
+            None => Ok(format!("\n{}", self.to_sgf().trim_end())),
+        }
+    }
+
+    fn check_no_moves(&self) -> CommandResult<()> {
+        if self.moves.is_empty() {
+            Ok(())
+        } else {
+            Err(BOARD_NOT_EMPTY.to_string())
+        }
+    }
+
+    pub(crate) fn set_handicap(&mut self, vertices: &[Vertex]) {
+        self.handicap = vertices.to_vec();
+        self.start = self.board.clone();
+    }
+
+    /// Carry out one of the standard commands which only affect the game's
+    /// state, returning the response payload.
+    ///
+    /// Returns `None` for commands a `Game` can't handle by itself (e.g.
+    /// `genmove` or `name`).
+    pub fn execute(&mut self, command: &StandardCommand) -> Option<CommandResult<String>> {
+        let result = match *command {
+            StandardCommand::BoardSize(size) => self.boardsize(size).map(empty),
+            StandardCommand::ClearBoard => {
+                self.clear_board();
+                Ok(String::new())
+            }
+            StandardCommand::Komi(komi) => {
+                self.set_komi(komi);
+                Ok(String::new())
+            }
+            StandardCommand::Play(mv) => self.play(mv).map(empty),
+            StandardCommand::Undo => self.undo().map(empty),
+            StandardCommand::FixedHandicap(n) => self.fixed_handicap(n).map(|v| engine::join(&v)),
+            StandardCommand::PlaceFreeHandicap(n) => {
+                self.place_free_handicap(n).map(|v| engine::join(&v))
+            }
+            StandardCommand::SetFreeHandicap(ref vertices) => {
+                self.set_free_handicap(vertices).map(empty)
+            }
+            StandardCommand::ShowBoard => Ok(format!("\n{}", self.board)),
+            StandardCommand::LoadSgf { ref filename, move_number } => {
+                self.loadsgf(filename, move_number).map(|color| color.to_string())
+            }
+            _ => return None,
+        };
+
+        Some(result)
+    }
+
+    /// Carry out a command, the same as `execute()`, but straight from a
+    /// `RawCommand`. This also understands `gg-undo`, which takes an
+    /// optional number of moves to undo, and `printsgf`, which takes an
+    /// optional filename.
+    ///
+    /// Commands with invalid arguments fail with `syntax error`.
+    pub fn execute_raw(&mut self, raw: &RawCommand) -> Option<CommandResult<String>> {
+        if raw.name.eq_ignore_ascii_case("gg-undo") {
+            let count = match raw.args.len() {
+                0 => Ok(1),
+                1 => raw.arg::<usize>(0).map_err(|_| SYNTAX_ERROR.to_string()),
+                _ => Err(SYNTAX_ERROR.to_string()),
+            };
+
+            return Some(count.and_then(|count| self.undo_moves(count)).map(empty));
+        }
+
+        if raw.name.eq_ignore_ascii_case("printsgf") {
+            return match raw.args.len() {
+                0 => Some(self.printsgf(None)),
+                1 => Some(self.printsgf(Some(&raw.args[0]))),
+                _ => Some(Err(SYNTAX_ERROR.to_string())),
+            };
+        }
+
+        match StandardCommand::try_from(raw) {
+            Ok(cmd) => self.execute(&cmd),
+            Err(ref e) if matches!(*e.kind(), ErrorKind::UnknownCommand(_)) => None,
+            Err(_) => Some(Err(SYNTAX_ERROR.to_string())),
+        }
+    }
+}
+
+impl Default for Game {
+    /// An empty 19x19 board.
+    fn default() -> Game {
+        Game::new(DEFAULT_BOARD_SIZE).expect("The default board size is always valid")
+    }
+}
+
+fn empty(_: ()) -> String {
+    String::new()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn run(game: &mut Game, line: &str) -> Option<CommandResult<String>> {
+        let raw: RawCommand = parse(line).unwrap();
+        game.execute_raw(&raw)
+    }
+
+    fn mv(color: Color, vertex: &str) -> Move {
+        Move::new(color, vertex.parse().unwrap())
+    }
+
+    #[test]
+    fn undo_restores_captured_stones() {
+        let mut game = Game::new(9).unwrap();
+        game.play(mv(Color::White, "A1")).unwrap();
+        game.play(mv(Color::Black, "A2")).unwrap();
+        game.play(mv(Color::Black, "B1")).unwrap();
+        assert_eq!(game.board().get("A1".parse().unwrap()), None);
+        assert_eq!(game.board().captures(Color::Black), 1);
+
+        game.undo().unwrap();
+
+        assert_eq!(game.board().get("A1".parse().unwrap()), Some(Color::White));
+        assert_eq!(game.board().captures(Color::Black), 0);
+        assert_eq!(game.moves().len(), 2);
+    }
+
+    #[test]
+    fn cannot_undo_past_the_start() {
+        let mut game = Game::new(9).unwrap();
+        game.fixed_handicap(2).unwrap();
+
+        assert_eq!(game.undo().unwrap_err(), "cannot undo");
+        assert_eq!(game.board().stones(Color::Black).len(), 2);
+    }
+
+    #[test]
+    fn replay_to_any_move_number() {
+        let mut game = Game::new(9).unwrap();
+        for &(color, vertex) in &[(Color::Black, "E5"), (Color::White, "C3"), (Color::Black, "G7")] {
+            game.play(mv(color, vertex)).unwrap();
+        }
+
+        assert!(game.board_at(0).unwrap().is_empty());
+        assert_eq!(game.board_at(2).unwrap().stones(Color::Black), vec!["E5".parse().unwrap()]);
+        assert_eq!(game.board_at(3).as_ref(), Some(game.board()));
+        assert_eq!(game.board_at(4), None);
+    }
+
+    #[test]
+    fn score_with_komi() {
+        let mut game = Game::new(5).unwrap();
+        game.set_komi(0.5);
+        for vertex in &["C1", "C2", "C3", "C4", "C5"] {
+            game.play(mv(Color::Black, vertex)).unwrap();
+            game.play(mv(Color::White, "pass")).unwrap();
+        }
+        game.play(mv(Color::Black, "pass")).unwrap();
+        game.play(mv(Color::White, "E5")).unwrap();
+
+        assert_eq!(game.score(&[], Rules::Area), GameResult::Win(Color::Black, 13.5));
+        assert_eq!(game.score(&["E5".parse().unwrap()], Rules::Territory).to_string(),
+                   "B+20.5");
+    }
+
+    #[test]
+    fn handicap_games() {
+        let mut game = Game::new(19).unwrap();
+
+        assert_eq!(run(&mut game, "fixed_handicap 4"), Some(Ok("D4 Q16 D16 Q4".to_string())));
+        assert_eq!(game.to_play(), Color::White);
+
+        run(&mut game, "play white C3").unwrap().unwrap();
+        assert_eq!(run(&mut game, "fixed_handicap 2"),
+                   Some(Err("board not empty".to_string())));
+        assert_eq!(game.to_play(), Color::Black);
+    }
+
+    #[test]
+    fn free_handicaps_can_place_fewer_stones_than_requested() {
+        let mut game = Game::new(9).unwrap();
+
+        let got = game.place_free_handicap(12).unwrap();
+
+        assert_eq!(got.len(), 9);
+        assert_eq!(game.handicap(), &got[..]);
+        assert_eq!(game.board().stones(Color::Black).len(), 9);
+        assert_eq!(game.to_play(), Color::White);
+    }
+
+    #[test]
+    fn execute_commands() {
+        let mut game = Game::default();
+
+        assert_eq!(run(&mut game, "boardsize 26"), Some(Err("unacceptable size".to_string())));
+        assert_eq!(run(&mut game, "komi 6.5"), Some(Ok(String::new())));
+        assert_eq!(run(&mut game, "boardsize 5"), Some(Ok(String::new())));
+        assert_eq!(game.komi(), 6.5);
+
+        assert_eq!(run(&mut game, "play black C3"), Some(Ok(String::new())));
+        assert_eq!(run(&mut game, "play white C3"), Some(Err("illegal move".to_string())));
+        assert_eq!(run(&mut game, "play white"), Some(Err("syntax error".to_string())));
+        assert_eq!(run(&mut game, "gg-undo many"), Some(Err("syntax error".to_string())));
+        assert!(run(&mut game, "showboard").unwrap().unwrap().starts_with("\n   A B C D E"));
+        assert_eq!(run(&mut game, "genmove white"), None);
+        assert_eq!(run(&mut game, "frobnicate"), None);
+
+        assert_eq!(run(&mut game, "GG-Undo"), Some(Ok(String::new())));
+        assert!(game.board().is_empty());
+        assert!(run(&mut game, "PRINTSGF").unwrap().unwrap().starts_with("\n(;GM[1]"));
+
+        run(&mut game, "play black C3").unwrap().unwrap();
+        run(&mut game, "clear_board").unwrap().unwrap();
+        assert!(game.moves().is_empty());
+        assert_eq!(game.komi(), 6.5);
+    }
+}
@@ -0,0 +1,123 @@
+//! Where handicap stones go.
+//!
+//! The fixed placements follow section 4.1.1 of the GTP v2 specification,
+//! and are what `Board::fixed_handicap()` uses.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use go_text_protocol::handicap::fixed_placement;
+//!
+//! let stones: Vec<String> = fixed_placement(19, 5).unwrap()
+//!     .iter()
+//!     .map(|v| v.to_string())
+//!     .collect();
+//! assert_eq!(stones, vec!["D4", "Q16", "D16", "Q4", "K10"]);
+//!
+//! assert_eq!(fixed_placement(19, 10).unwrap_err(), "invalid number of stones");
+//! ```
+
+use crate::engine::{CommandResult, INVALID_NUMBER_OF_STONES};
+use crate::values::Vertex;
+
+/// The most fixed handicap stones which can be placed on a board, or `0` if
+/// the board is too small for fixed handicaps.
+///
+/// Boards smaller than 7x7 don't get any, 7x7 and even sized boards have no
+/// middle point to use so they're limited to 4, and everything else can have
+/// up to 9.
+pub fn max_fixed_handicap(size: u32) -> u32 {
+    if size < 7 {
+        0
+    } else if size == 7 || size % 2 == 0 {
+        4
+    } else {
+        9
+    }
+}
+
+/// The vertices to place `stones` fixed handicap stones on, in the order
+/// they're listed by the spec.
+///
+/// Fails with `invalid number of stones` unless there are between 2 and
+/// `max_fixed_handicap(size)` stones.
+pub fn fixed_placement(size: u32, stones: u32) -> CommandResult<Vec<Vertex>> {
+    if stones < 2 || stones > max_fixed_handicap(size) {
+        return Err(INVALID_NUMBER_OF_STONES.to_string());
+    }
+
+    // 3-3 points on small boards, 4-4 points from 13x13 up
+    let low = if size >= 13 { 3 } else { 2 };
+    let high = size as u8 - 1 - low;
+    let middle = (size / 2) as u8;
+
+    let point = |column, row| Vertex::Point { column, row };
+
+    let mut vertices = vec![point(low, low), point(high, high), point(low, high), point(high, low)];
+
+    if stones >= 6 {
+        vertices.push(point(low, middle));
+        vertices.push(point(high, middle));
+    }
+    if stones >= 8 {
+        vertices.push(point(middle, low));
+        vertices.push(point(middle, high));
+    }
+
+    // from 5 stones up, an odd number means one goes in the middle
+    if stones >= 5 && stones % 2 == 1 {
+        vertices.truncate(stones as usize - 1);
+        vertices.push(point(middle, middle));
+    } else {
+        vertices.truncate(stones as usize);
+    }
+
+    Ok(vertices)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(size: u32, stones: u32) -> Vec<String> {
+        fixed_placement(size, stones).unwrap().iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn the_spec_table() {
+        let inputs = vec![(2, "D4 Q16"),
+                          (3, "D4 Q16 D16"),
+                          (4, "D4 Q16 D16 Q4"),
+                          (5, "D4 Q16 D16 Q4 K10"),
+                          (6, "D4 Q16 D16 Q4 D10 Q10"),
+                          (7, "D4 Q16 D16 Q4 D10 Q10 K10"),
+                          (8, "D4 Q16 D16 Q4 D10 Q10 K4 K16"),
+                          (9, "D4 Q16 D16 Q4 D10 Q10 K4 K16 K10")];
+
+        for (stones, should_be) in inputs {
+            assert_eq!(placement(19, stones).join(" "), should_be);
+        }
+    }
+
+    #[test]
+    fn smaller_boards_use_the_3_3_points() {
+        assert_eq!(placement(9, 9).join(" "), "C3 G7 C7 G3 C5 G5 E3 E7 E5");
+        assert_eq!(placement(13, 5).join(" "), "D4 K10 D10 K4 G7");
+        assert_eq!(placement(7, 4).join(" "), "C3 E5 C5 E3");
+    }
+
+    #[test]
+    fn invalid_numbers_of_stones() {
+        let inputs = vec![(19, 0), (19, 1), (19, 10), (7, 5), (10, 5), (6, 2)];
+
+        for (size, stones) in inputs {
+            assert_eq!(fixed_placement(size, stones).unwrap_err(),
+                       "invalid number of stones",
+                       "{} stones on {}x{}",
+                       stones,
+                       size,
+                       size);
+        }
+    }
+}
@@ -2,39 +2,51 @@
 //!
 //! If you want to check out the parser and how it works under the hood,
 //! consult the `parser` module.
+//!
+//! Async versions of the engine session loop and controller are available
+//! in the `async_io` module when the `tokio` feature is enabled.
 
 #![deny(missing_docs)]
 
-#[macro_use]
-extern crate error_chain;
-extern crate regex;
+extern crate go_text_protocol_derive;
+extern crate smallvec;
+
+// Lets the code generated by `#[derive(GtpCommand)]` refer to
+// `::go_text_protocol` from inside this crate.
+extern crate self as go_text_protocol;
 
+#[macro_use]
+mod errors;
 #[macro_use]
 mod macros;
+#[cfg(feature = "tokio")]
+pub mod async_io;
+pub mod board;
+pub mod commands;
+pub mod controller;
+pub mod diagnostic;
+pub mod engine;
+pub mod game;
+pub mod handicap;
 pub mod parser;
-
-pub use errors::*;
-pub use parser::{RawCommand, Parser, parse};
+pub mod reader;
+pub mod regression;
+pub mod response;
+pub mod scoring;
+pub mod sgf;
+pub mod time;
+pub mod values;
+
+pub use crate::commands::{GtpCommand, StandardCommand};
+pub use crate::engine::{Engine, run_session};
+pub use crate::errors::*;
+pub use crate::parser::{RawCommand, RawCommandRef, Parser, parse, parse_borrowed};
+pub use crate::reader::CommandReader;
+pub use crate::response::{Response, Status, read_response, write_response};
+pub use crate::values::{Boolean, Color, Float, Int, Move, StoneStatus, Vertex};
+pub use go_text_protocol_derive::GtpCommand;
 
 custom_command!(#[doc = "My custom command"]
                enum MyCommand {
                    Foo,
                });
-
-
-mod errors {
-    error_chain!{
-
-        foreign_links {
-            Regex(::regex::Error) #[doc = "A regex error"];
-        }
-
-        errors {
-            /// Whitespace was expected.
-            NoWhitespace {}
-
-            /// The string doesn't contain a command.
-            NoCommand {}
-        }
-    }
-}
@@ -1,21 +1,122 @@
-// TODO: Write a macro which can turn any variant into its corresponding type
-macro_rules! variant {
-    ($name:ident) => {
-        name
+/// Turns a single variant of a `custom_command!()` into its enum definition
+/// and the code needed to convert a `RawCommand` into it.
+///
+/// This munches through the variants one at a time, accumulating the variant
+/// definitions and conversion checks, then emits the enum and its `TryFrom`
+/// impl once there's nothing left. It's an implementation detail of
+/// `custom_command!()` and shouldn't be used directly.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __custom_command_variant {
+    // Everything has been munched, emit the enum and its conversion
+    (@munch $raw:ident [$($attr:tt)*] $name:ident [$($variants:tt)*] [$($checks:tt)*]) => {
+        $($attr)*
+        #[derive(Clone, PartialEq, Hash, Debug)]
+        #[allow(missing_docs)]
+        pub enum $name {
+            $($variants)*
+
+            /// A command which doesn't currently have a variant.
+            UnrecognisedCommand(Option<u32>, String, Vec<String>),
+        }
+
+        impl ::std::convert::TryFrom<$crate::RawCommand> for $name {
+            type Error = $crate::Error;
+
+            fn try_from($raw: $crate::RawCommand) -> $crate::Result<Self> {
+                $($checks)*
+
+                // If we got this far then there were no matches
+                Ok($name::UnrecognisedCommand($raw.count, $raw.name, $raw.args))
+            }
+        }
     };
-    ($name:ident(count)) => {
-        // TODO: Finish this.
+
+    // A plain unit variant, any arguments are ignored
+    (@munch $raw:ident $attrs:tt $name:ident [$($variants:tt)*] [$($checks:tt)*]
+     $(#[$vattr:meta])* $command:ident, $($rest:tt)*) => {
+        $crate::__custom_command_variant!(@munch $raw $attrs $name
+            [$($variants)* $(#[$vattr])* $command,]
+            [$($checks)*
+             if $raw.name.eq_ignore_ascii_case(stringify!($command)) {
+                 return Ok($name::$command);
+             }]
+            $($rest)*);
     };
-    ($name:ident(count, args)) => {
-        // TODO: Finish this.
+
+    (@munch $raw:ident $attrs:tt $name:ident [$($variants:tt)*] [$($checks:tt)*]
+     $(#[$vattr:meta])* $command:ident(count), $($rest:tt)*) => {
+        $crate::__custom_command_variant!(@munch $raw $attrs $name
+            [$($variants)* $(#[$vattr])* $command(Option<u32>),]
+            [$($checks)*
+             if $raw.name.eq_ignore_ascii_case(stringify!($command)) {
+                 return Ok($name::$command($raw.count));
+             }]
+            $($rest)*);
+    };
+
+    (@munch $raw:ident $attrs:tt $name:ident [$($variants:tt)*] [$($checks:tt)*]
+     $(#[$vattr:meta])* $command:ident(args), $($rest:tt)*) => {
+        $crate::__custom_command_variant!(@munch $raw $attrs $name
+            [$($variants)* $(#[$vattr])* $command(Vec<String>),]
+            [$($checks)*
+             if $raw.name.eq_ignore_ascii_case(stringify!($command)) {
+                 return Ok($name::$command($raw.args));
+             }]
+            $($rest)*);
+    };
+
+    (@munch $raw:ident $attrs:tt $name:ident [$($variants:tt)*] [$($checks:tt)*]
+     $(#[$vattr:meta])* $command:ident(count, args), $($rest:tt)*) => {
+        $crate::__custom_command_variant!(@munch $raw $attrs $name
+            [$($variants)* $(#[$vattr])* $command(Option<u32>, Vec<String>),]
+            [$($checks)*
+             if $raw.name.eq_ignore_ascii_case(stringify!($command)) {
+                 return Ok($name::$command($raw.count, $raw.args));
+             }]
+            $($rest)*);
     };
-    ($name:ident(args)) => {
-        // TODO: Finish this.
+
+    // Typed arguments, with the count
+    (@munch $raw:ident $attrs:tt $name:ident [$($variants:tt)*] [$($checks:tt)*]
+     $(#[$vattr:meta])* $command:ident(count, $($ty:ty),+), $($rest:tt)*) => {
+        $crate::__custom_command_variant!(@munch $raw $attrs $name
+            [$($variants)* $(#[$vattr])* $command(Option<u32>, $($ty),+),]
+            [$($checks)*
+             if $raw.name.eq_ignore_ascii_case(stringify!($command)) {
+                 let expected = [$(stringify!($ty)),+].len();
+                 $raw.check_arg_count(expected, expected)?;
+
+                 let mut indices = 0..;
+                 return Ok($name::$command($raw.count,
+                                           $($raw.arg::<$ty>(indices.next().unwrap())?),+));
+             }]
+            $($rest)*);
+    };
+
+    // Typed arguments, without the count
+    (@munch $raw:ident $attrs:tt $name:ident [$($variants:tt)*] [$($checks:tt)*]
+     $(#[$vattr:meta])* $command:ident($($ty:ty),+), $($rest:tt)*) => {
+        $crate::__custom_command_variant!(@munch $raw $attrs $name
+            [$($variants)* $(#[$vattr])* $command($($ty),+),]
+            [$($checks)*
+             if $raw.name.eq_ignore_ascii_case(stringify!($command)) {
+                 let expected = [$(stringify!($ty)),+].len();
+                 $raw.check_arg_count(expected, expected)?;
+
+                 let mut indices = 0..;
+                 return Ok($name::$command($($raw.arg::<$ty>(indices.next().unwrap())?),+));
+             }]
+            $($rest)*);
     };
 }
 
 /// A macro which allows you to create your own custom command.
 ///
+/// > **Note:** `#[derive(GtpCommand)]` is a more flexible alternative which
+/// > supports renaming commands (e.g. `gogui-analyze_commands`). See the
+/// > `GtpCommand` trait for more.
+///
 /// Given something like this:
 ///
 /// ```rust,ignore
@@ -40,57 +141,135 @@ macro_rules! variant {
 /// Note the `UnrecognisedCommand` variant, this acts as a catch all if you
 /// are sent an unknown command.
 ///
-/// The macro also provides a `std::convert::From` impl so you can convert
+/// The macro also provides a `std::convert::TryFrom` impl so you can convert
 /// from a `RawCommand` into your command. This allows the parser to
 /// transparently convert a line from the `Go Text Protocol` into your custom
 /// type.
 ///
 ///
-/// # Note
+/// # Capturing The Count And Arguments
 ///
-/// At the moment the macro isn't complete. In the future I'd like to be able
-/// to do something like the following and have the command automatically
-/// attach the count and/or args accordingly.
+/// Variants can also capture the command's `count` and/or `args`, or a list
+/// of argument types which will be parsed (using `FromStr`) and validated
+/// during the conversion.
 ///
-/// ```rust,ignore
-/// custom_command!( enum MyCommand {
-///   Play(count, args),
-///   ShowBoard,
-///   BoardSize(args),
+/// ```rust
+/// #[macro_use]
+/// extern crate go_text_protocol;
+///
+/// use go_text_protocol::{Color, Vertex, parse};
+///
+/// custom_command!(enum MyCommand {
+///   /// Place a stone on the board.
+///   Play(count, Color, Vertex),
+///   GenMove(Color),
+///   Echo(args),
+///   Undo(count),
+///   Debug(count, args),
 ///   Quit,
-/// })
+/// });
+///
+/// fn main() {
+///   let got: MyCommand = parse("7 play black D5").unwrap();
+///   let d5 = Vertex::Point { column: 3, row: 4 };
+///   assert_eq!(got, MyCommand::Play(Some(7), Color::Black, d5));
+///
+///   let got: MyCommand = parse("echo hello world").unwrap();
+///   assert_eq!(got, MyCommand::Echo(vec!["hello".to_string(), "world".to_string()]));
+///
+///   // Arguments which are missing or the wrong type are an error
+///   assert!(parse::<MyCommand>("play black").is_err());
+///   assert!(parse::<MyCommand>("genmove purple").is_err());
+/// }
 /// ```
+///
+/// Unit variants and the `count`/`args` forms accept any number of
+/// arguments, whereas typed variants require exactly one argument per type.
+/// Each type is parsed from a single argument, so a `Move` should be written
+/// as `Color, Vertex`. The argument types also need to implement `Hash` (use
+/// `Float` rather than `f32`).
 #[macro_export]
 macro_rules! custom_command {
-    ( $(#[$attr:meta])* enum $name:ident { $($command:tt,)* } ) => {
-        $(
-            #[$attr]
-            )*
-        #[derive(Clone, PartialEq, Hash, Debug)]
-        #[allow(missing_docs)]
-        pub enum $name {
-            $(
-                $command,
-                )*
+    ( $(#[$attr:meta])* enum $name:ident { $($body:tt)* } ) => {
+        $crate::__custom_command_variant!(@munch raw [$(#[$attr])*] $name [] [] $($body)*);
+    }
+}
 
-            /// A command which doesn't currently have a variant.
-            UnrecognisedCommand(Option<u32>, String, Vec<String>),
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::*;
+    use crate::parser::parse;
+    use crate::values::{Color, Float, Vertex};
+
+    custom_command!(enum TestCommand {
+        Play(count, Color, Vertex),
+        Komi(Float),
+        Echo(args),
+        Undo(count),
+        Debug(count, args),
+        Quit,
+    });
+
+    #[test]
+    fn commands_can_be_hashed() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        fn hash_of(src: &str) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            parse::<TestCommand>(src).unwrap().hash(&mut hasher);
+            hasher.finish()
         }
 
+        assert_eq!(hash_of("quit"), hash_of("QUIT"));
+        assert_eq!(hash_of("komi 7.5"), hash_of("komi 7.50"));
+        assert_eq!(hash_of("komi 0"), hash_of("komi -0"));
+        assert_ne!(hash_of("komi 7.5"), hash_of("komi 6.5"));
+    }
+
+    #[test]
+    fn unit_variants_are_case_insensitive() {
+        let got: TestCommand = parse("QUIT").unwrap();
+        assert_eq!(got, TestCommand::Quit);
+    }
 
-        impl ::std::convert::From<$crate::RawCommand> for $name {
-            fn from(raw: $crate::RawCommand) -> Self {
-                let name_as_lower = raw.name.to_lowercase();
+    #[test]
+    fn capture_count_and_args() {
+        let got: TestCommand = parse("3 undo").unwrap();
+        assert_eq!(got, TestCommand::Undo(Some(3)));
 
-                $(
-                    if name_as_lower == stringify!($command).to_lowercase() {
-                        return $name::$command;
-                    }
-                    )*
+        let got: TestCommand = parse("4 debug a b").unwrap();
+        assert_eq!(got, TestCommand::Debug(Some(4), vec!["a".to_string(), "b".to_string()]));
 
-                // If we got this far then there were no matches
-                $name::UnrecognisedCommand(raw.count, raw.name, raw.args)
-            }
+        let got: TestCommand = parse("echo").unwrap();
+        assert_eq!(got, TestCommand::Echo(vec![]));
+    }
+
+    #[test]
+    fn typed_arguments_are_validated() {
+        let got: TestCommand = parse("play w pass").unwrap();
+        assert_eq!(got, TestCommand::Play(None, Color::White, Vertex::Pass));
+
+        let got: TestCommand = parse("komi 7.5").unwrap();
+        assert_eq!(got, TestCommand::Komi(Float(7.5)));
+
+        match *parse::<TestCommand>("play white").unwrap_err().kind() {
+            ErrorKind::WrongArgumentCount(..) => {}
+            ref other => panic!("Unexpected error: {:?}", other),
         }
+        match *parse::<TestCommand>("play white Q99").unwrap_err().kind() {
+            ErrorKind::InvalidArgument(1, _) => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_commands_are_still_caught() {
+        let got: TestCommand = parse("1 showboard now").unwrap();
+        assert_eq!(got,
+                   TestCommand::UnrecognisedCommand(Some(1),
+                                                    "showboard".to_string(),
+                                                    vec!["now".to_string()]));
     }
 }
@@ -40,20 +40,125 @@
 //! }
 //! ```
 
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
-use errors::*;
-use regex::Regex;
+use crate::errors::*;
+use crate::values::Move;
+use smallvec::SmallVec;
 
 /// Parse a single line and extract a command.
 ///
-/// This function is generic, so you can get any type which can be coerced from
-/// a `RawCommand` using `raw_command.into()`.
+/// This function is generic, so you can get any type which can be created
+/// from a `RawCommand` using `TryFrom` (which includes anything implementing
+/// `From<RawCommand>`).
 pub fn parse<C>(src: &str) -> Result<C>
-    where C: From<RawCommand>
+    where C: TryFrom<RawCommand>,
+          C::Error: Into<Error>
 {
     let parser = Parser::new(src);
-    parser.parse().map(|c| c.into())
+    let raw = parser.parse()?;
+    C::try_from(raw).map_err(Into::into)
+}
+
+/// Parse a single line without copying anything, borrowing the command's name
+/// and arguments straight from `src`.
+///
+/// Comments, tabs, and line endings are handled the same as `parse()`, but
+/// any other control character means the line would need to be rewritten
+/// (see `preprocess()`), so you'll get an `ErrorKind::ControlCharacter`
+/// instead. Use `parse()` if you need to accept that sort of input.
+///
+/// ```rust
+/// use go_text_protocol::{RawCommand, parse_borrowed};
+///
+/// let line = String::from("3 play black D5\r\n");
+/// let cmd = parse_borrowed(&line).unwrap();
+///
+/// assert_eq!(cmd.count, Some(3));
+/// assert_eq!(cmd.name, "play");
+/// assert_eq!(&cmd.args[..], &["black", "D5"]);
+///
+/// // and you can always get an owned version if you need one
+/// let owned: RawCommand = cmd.into();
+/// assert_eq!(owned.args, vec!["black", "D5"]);
+/// ```
+pub fn parse_borrowed(src: &str) -> Result<RawCommandRef<'_>> {
+    let line = match src.find('#') {
+        Some(ix) => &src[..ix],
+        None => src,
+    };
+    let line = line.trim_end_matches(['\r', '\n']);
+
+    if let Some(position) = line.find(|c: char| c.is_ascii_control() && c != '\t' && c != '\n') {
+        // ASCII control characters are always a single byte
+        let span = Span::new(position, position + 1);
+        bail!(Error::new(ErrorKind::ControlCharacter(position)).with_span(span));
+    }
+
+    Scanner::new(line).scan().map(|scanned| scanned.command)
+}
+
+/// Preprocess a line of input as described in section 3.1 of the GTP v2
+/// specification.
+///
+/// This will:
+///
+/// - Remove everything after a `#` (comments)
+/// - Delete all control characters other than `HT` and `LF`
+/// - Convert every `HT` into a space
+///
+/// If the line is empty or only contains whitespace after all that, it should
+/// be discarded and `None` is returned.
+///
+/// ```rust
+/// use go_text_protocol::parser::preprocess;
+///
+/// assert_eq!(preprocess("1 genmove\tblack # think hard"),
+///            Some("1 genmove black ".to_string()));
+/// assert_eq!(preprocess("   # just a comment"), None);
+/// ```
+pub fn preprocess(line: &str) -> Option<String> {
+    let without_comment = match line.find('#') {
+        Some(ix) => &line[..ix],
+        None => line,
+    };
+
+    let cleaned: String = without_comment.chars()
+        .filter(|&c| !c.is_ascii_control() || c == '\t' || c == '\n')
+        .map(|c| if c == '\t' { ' ' } else { c })
+        .collect();
+
+    if cleaned.trim().is_empty() {
+        None
+    } else {
+        Some(cleaned)
+    }
+}
+
+/// Split the id (exactly as it was written) off the front of a line,
+/// returning it along with the rest of the line.
+///
+/// Unlike the parser, this places no limit on how long the id can be.
+///
+/// ```rust
+/// use go_text_protocol::parser::split_id;
+///
+/// assert_eq!(split_id("007 name"), (Some("007"), " name"));
+/// assert_eq!(split_id("99999999999 name"), (Some("99999999999"), " name"));
+/// assert_eq!(split_id("name"), (None, "name"));
+/// ```
+pub fn split_id(line: &str) -> (Option<&str>, &str) {
+    let trimmed = line.trim_start_matches(is_separator);
+    let digits = trimmed.bytes().take_while(u8::is_ascii_digit).count();
+    let (id, rest) = trimmed.split_at(digits);
+
+    if digits > 0 && rest.chars().next().map(is_separator).unwrap_or(true) {
+        (Some(id), rest)
+    } else {
+        (None, line)
+    }
 }
 
 /// A raw command containing the command name, an optional count, and its
@@ -70,115 +175,351 @@ pub struct RawCommand {
     pub args: Vec<String>,
 }
 
+impl RawCommand {
+    /// Parse the argument at `index` as some type `T` (usually one of the
+    /// types in the `values` module).
+    ///
+    /// ```rust
+    /// use go_text_protocol::{RawCommand, Float, parse};
+    ///
+    /// let cmd: RawCommand = parse("komi 6.5").unwrap();
+    ///
+    /// assert_eq!(cmd.arg::<Float>(0).unwrap(), Float(6.5));
+    /// assert!(cmd.arg::<Float>(1).is_err());
+    /// ```
+    pub fn arg<T>(&self, index: usize) -> Result<T>
+        where T: FromStr,
+              T::Err: ::std::error::Error + Send + Sync + 'static
+    {
+        let arg = self.args.get(index).ok_or(ErrorKind::MissingArgument(index))?;
+        arg.parse().chain_err(|| ErrorKind::InvalidArgument(index, arg.clone()))
+    }
+
+    /// Make sure the command has between `min` and `max` arguments
+    /// (inclusive), returning an `ErrorKind::WrongArgumentCount` if it
+    /// doesn't. Use `usize::MAX` for commands without an upper limit.
+    pub fn check_arg_count(&self, min: usize, max: usize) -> Result<()> {
+        let got = self.args.len();
+
+        if min <= got && got <= max {
+            Ok(())
+        } else {
+            let expected = if min == max {
+                min.to_string()
+            } else if max == usize::MAX {
+                format!("at least {}", min)
+            } else {
+                format!("{} to {}", min, max)
+            };
+
+            Err(ErrorKind::WrongArgumentCount(self.name.clone(), expected, got).into())
+        }
+    }
+
+    /// Parse the two arguments starting at `index` as a `Move`.
+    pub fn move_arg(&self, index: usize) -> Result<Move> {
+        let color = self.arg(index)?;
+        let vertex = self.arg(index + 1)?;
+        Ok(Move::new(color, vertex))
+    }
+}
+
+impl Display for RawCommand {
+    /// Write the command back out in its wire format (without the trailing
+    /// newline).
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if let Some(count) = self.count {
+            write!(f, "{} ", count)?;
+        }
+
+        write!(f, "{}", self.name)?;
+
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A borrowed version of `RawCommand`, as returned by `parse_borrowed()`.
+///
+/// The name and arguments point into the original line, and most commands
+/// have few enough arguments that they can be stored without allocating.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RawCommandRef<'a> {
+    /// An optional number attached to the command.
+    pub count: Option<u32>,
+
+    /// The name of the command itself.
+    pub name: &'a str,
+
+    /// Zero or more arguments for the command.
+    pub args: SmallVec<[&'a str; 4]>,
+}
+
+impl<'a> RawCommandRef<'a> {
+    /// Parse the argument at `index` as some type `T`, the same as
+    /// `RawCommand::arg()`.
+    pub fn arg<T>(&self, index: usize) -> Result<T>
+        where T: FromStr,
+              T::Err: ::std::error::Error + Send + Sync + 'static
+    {
+        let arg = self.args.get(index).ok_or(ErrorKind::MissingArgument(index))?;
+        arg.parse().chain_err(|| ErrorKind::InvalidArgument(index, arg.to_string()))
+    }
+
+    /// Copy the name and arguments into an owned `RawCommand`.
+    pub fn into_owned(self) -> RawCommand {
+        RawCommand {
+            count: self.count,
+            name: self.name.to_string(),
+            args: self.args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+impl<'a> From<RawCommandRef<'a>> for RawCommand {
+    fn from(other: RawCommandRef<'a>) -> RawCommand {
+        other.into_owned()
+    }
+}
+
+impl<'a> From<&'a RawCommand> for RawCommandRef<'a> {
+    fn from(other: &'a RawCommand) -> RawCommandRef<'a> {
+        RawCommandRef {
+            count: other.count,
+            name: &other.name,
+            args: other.args.iter().map(|arg| arg.as_str()).collect(),
+        }
+    }
+}
+
+impl<'a> Display for RawCommandRef<'a> {
+    /// Write the command back out in its wire format (without the trailing
+    /// newline).
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if let Some(count) = self.count {
+            write!(f, "{} ", count)?;
+        }
+
+        write!(f, "{}", self.name)?;
+
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Does this character separate tokens? GTP only uses spaces and tabs (once
+/// preprocessing has removed everything else), plus the newline at the end
+/// of the line. Other Unicode whitespace is part of a token.
+fn is_separator(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\n'
+}
+
 /// A line parser.
 pub struct Parser {
     src: String,
-    pointer: usize,
 }
 
 
 impl Parser {
     /// Create a new parser to parse a line.
     pub fn new(line: &str) -> Parser {
-        Parser {
-            src: line.to_string(),
-            pointer: 0,
-        }
+        Parser { src: line.to_string() }
     }
 
     /// Parse the source string into a `RawCommand`.
-    pub fn parse(mut self) -> Result<RawCommand> {
-        // Try to lex the provided string into its optional count, command, and
-        // arguments.
-        let (count, mut identifiers) = self.lex()
-            .chain_err(|| "Failed to parse the line into tokens")?;
-
-        // Make sure we got at least 1 identifier (i.e. the command name itself)
-        if identifiers.len() < 1 {
-            Err(ErrorKind::NoCommand.into())
-        } else {
-            let args = identifiers.split_off(1);
+    ///
+    /// The line is run through `preprocess()` first. If nothing is left
+    /// afterwards you'll get an `ErrorKind::EmptyLine` error, which means the
+    /// line should be silently ignored rather than responded to.
+    pub fn parse(self) -> Result<RawCommand> {
+        let line = match preprocess(&self.src) {
+            Some(line) => line,
+            None => bail!(ErrorKind::EmptyLine),
+        };
+
+        let scanned = Scanner::new(&line).scan();
 
-            Ok(RawCommand {
-                   count: count,
-                   name: identifiers[0].clone(),
-                   args: args,
-               })
+        match scanned {
+            Ok(scanned) => Ok(scanned.command.into_owned()),
+            Err(e) => {
+                match e.span() {
+                    Some(span) => Err(e.with_span(original_span(&self.src, span))),
+                    None => Err(e),
+                }
+            }
         }
     }
+}
 
-    /// Does lexical analysis.
-    ///
-    /// This breaks the input string into an optional number (plus a space),
-    /// followed by a number of space delimited strings (the command and args).
-    fn lex(&mut self) -> Result<(Option<u32>, Vec<String>)> {
-        let mut tokens = vec![];
-        let mut count = None;
+/// Map a span in `preprocess(line)` back onto the same text in `line`.
+///
+/// Preprocessing only ever deletes characters or swaps a tab for a space, so
+/// every remaining byte has a counterpart in the original line.
+fn original_span(line: &str, span: Span) -> Span {
+    let offsets: Vec<usize> = line.char_indices()
+        .filter(|&(_, c)| !c.is_ascii_control() || c == '\t' || c == '\n')
+        .flat_map(|(i, c)| i..i + c.len_utf8())
+        .collect();
+    let lookup = |ix: usize| offsets.get(ix).cloned().unwrap_or(line.len());
+
+    if span.is_empty() {
+        let start = lookup(span.start);
+        Span::new(start, start)
+    } else {
+        Span::new(lookup(span.start), lookup(span.end - 1) + 1)
+    }
+}
+
+/// Find where the command name and each of its arguments are in `line`, so
+/// errors raised while interpreting a `RawCommand` can be traced back to the
+/// text they came from.
+pub(crate) fn token_spans(line: &str) -> Option<(Span, SmallVec<[Span; 4]>)> {
+    let cleaned = preprocess(line)?;
+    let scanned = Scanner::new(&cleaned).scan().ok()?;
+
+    let name = original_span(line, scanned.name);
+    let args = scanned.args.iter().map(|&arg| original_span(line, arg)).collect();
 
-        if let Some(num) = self.read_number() {
-            count = Some(num);
-            self.skip_whitespace()?;
+    Some((name, args))
+}
+
+/// A scanned line, along with where each token was found.
+struct Scanned<'a> {
+    command: RawCommandRef<'a>,
+    name: Span,
+    args: SmallVec<[Span; 4]>,
+}
+
+/// Breaks a line into an optional id followed by a number of separator
+/// delimited tokens (the command and its arguments).
+///
+/// Both `Parser::parse()` and `parse_borrowed()` go through this, so they
+/// always agree on what a line means and which error it gets.
+struct Scanner<'a> {
+    src: &'a str,
+    pointer: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(src: &'a str) -> Scanner<'a> {
+        Scanner { src, pointer: 0 }
+    }
+
+    /// Scan the entire line.
+    fn scan(mut self) -> Result<Scanned<'a>> {
+        // leading whitespace isn't significant
+        self.skip_optional_whitespace();
+
+        if self.rest().is_empty() {
+            bail!(ErrorKind::EmptyLine);
+        }
+
+        let id_start = self.pointer;
+        let count = self.read_number()?;
+
+        if count.is_some() && !self.rest().is_empty() {
+            if let Err(e) = self.skip_whitespace() {
+                // point at the whole "12play" token
+                let length = self.rest().find(is_separator).unwrap_or(self.rest().len());
+                return Err(e.with_span(Span::new(id_start, self.pointer + length)));
+            }
         }
 
-        while let Some(next_token) = self.lex_identifier() {
-            tokens.push(next_token);
-            let _ = self.skip_whitespace();
+        let name_start = self.pointer;
+        let name = match self.lex_identifier() {
+            Some(name) => name,
+            None => {
+                let id_end = self.src.trim_end_matches(is_separator).len();
+                bail!(Error::new(ErrorKind::NoCommand).with_span(Span::new(id_end, id_end)));
+            }
+        };
+        let name_span = Span::new(name_start, self.pointer);
+
+        let mut args = SmallVec::new();
+        let mut arg_spans = SmallVec::new();
+        self.skip_optional_whitespace();
+
+        // every character is either a separator or part of an identifier, so
+        // this always consumes the entire line
+        let mut start = self.pointer;
+        while let Some(arg) = self.lex_identifier() {
+            args.push(arg);
+            arg_spans.push(Span::new(start, self.pointer));
+            self.skip_optional_whitespace();
+            start = self.pointer;
         }
 
-        Ok((count, tokens))
+        Ok(Scanned {
+               command: RawCommandRef { count, name, args },
+               name: name_span,
+               args: arg_spans,
+           })
+    }
+
+    /// The part of the source string which hasn't been scanned yet.
+    fn rest(&self) -> &'a str {
+        &self.src[self.pointer..]
     }
 
     /// Try to read a number from the source string, moving the pointer if a
     /// match is found.
-    fn read_number(&mut self) -> Option<u32> {
-        let pattern = Regex::new(r"^\d+").unwrap();
-        let substring = &self.src[self.pointer..];
-
-        match pattern.find(substring) {
-            None => None,
-            Some(mat) => {
-                let number_as_str = mat.as_str();
-                self.pointer += number_as_str.len();
-                let number = u32::from_str(number_as_str).unwrap();
-                Some(number)
-            }
+    fn read_number(&mut self) -> Result<Option<u32>> {
+        let num_digits = self.rest().bytes().take_while(u8::is_ascii_digit).count();
+
+        if num_digits == 0 {
+            return Ok(None);
         }
+
+        let digits = &self.rest()[..num_digits];
+        let number = u32::from_str(digits).map_err(|_| {
+                Error::new(ErrorKind::IdOverflow(digits.to_string()))
+                    .with_span(Span::new(self.pointer, self.pointer + num_digits))
+            })?;
+        self.pointer += num_digits;
+        Ok(Some(number))
     }
 
     /// Move the pointer past any whitespace, returning an error if there
     /// wasn't any.
     fn skip_whitespace(&mut self) -> Result<()> {
-        let pattern = Regex::new(r"^\s+").unwrap();
-        let substring = &self.src[self.pointer..];
-
-        let num_bytes_to_skip = match pattern.find(substring) {
-            None => 0,
-            Some(mat) => mat.as_str().len(),
-        };
-
-        self.pointer += num_bytes_to_skip;
-
-        if num_bytes_to_skip == 0 {
+        if self.skip_optional_whitespace() == 0 {
             Err(ErrorKind::NoWhitespace.into())
         } else {
             Ok(())
         }
     }
 
-    /// Try to match an identifier (any alphanumeric string).
-    fn lex_identifier(&mut self) -> Option<String> {
-        let pattern = Regex::new(r"^[\w\d]+").unwrap();
-        let substring = &self.src[self.pointer..];
+    /// Move the pointer past any whitespace, returning the number of bytes
+    /// skipped.
+    ///
+    /// Errors aren't free, so this is what the scanner uses when whitespace
+    /// is allowed but not required.
+    fn skip_optional_whitespace(&mut self) -> usize {
+        let rest = self.rest();
+        let num_bytes_to_skip = rest.len() - rest.trim_start_matches(is_separator).len();
 
-        match pattern.find(substring) {
-            None => None,
-            Some(mat) => {
-                let token = mat.as_str().to_string();
-                self.pointer += token.len();
-                Some(token)
-            }
+        self.pointer += num_bytes_to_skip;
+        num_bytes_to_skip
+    }
+
+    /// Try to match an identifier (any run of characters other than
+    /// separators).
+    fn lex_identifier(&mut self) -> Option<&'a str> {
+        let rest = self.rest();
+        let length = rest.find(is_separator).unwrap_or(rest.len());
+
+        if length == 0 {
+            return None;
         }
 
+        self.pointer += length;
+        Some(&rest[..length])
     }
 }
 
@@ -190,11 +531,11 @@ mod tests {
     #[test]
     fn lex_number() {
         let src = "123";
-        let mut lexer = Parser::new(src);
+        let mut lexer = Scanner::new(src);
         let should_be = 123;
 
         assert_eq!(lexer.pointer, 0);
-        let got = lexer.read_number();
+        let got = lexer.read_number().unwrap();
 
         assert_eq!(got, Some(should_be));
         assert_eq!(lexer.pointer, 3);
@@ -203,7 +544,7 @@ mod tests {
     #[test]
     fn lex_whitespace() {
         let src = "    ";
-        let mut lexer = Parser::new(src);
+        let mut lexer = Scanner::new(src);
 
         assert_eq!(lexer.pointer, 0);
         lexer.skip_whitespace().unwrap();
@@ -212,28 +553,27 @@ mod tests {
 
     #[test]
     fn lex_identifier() {
-        let src = "asd".to_string();
-        let mut lexer = Parser::new(src.as_str());
-        let should_be = src;
+        let src = "asd";
+        let mut lexer = Scanner::new(src);
 
         assert_eq!(lexer.pointer, 0);
         let got = lexer.lex_identifier();
         assert_eq!(lexer.pointer, 3);
 
-        assert_eq!(got, Some(should_be));
+        assert_eq!(got, Some(src));
     }
 
     #[test]
     fn lex_a_string() {
         let src = "123 hello";
-        let mut lexer = Parser::new(src);
-        let count_should_be = Some(123);
-        let identifiers_should_be = vec!["hello".to_string()];
+        let lexer = Scanner::new(src);
 
-        let (count, identifiers) = lexer.lex().unwrap();
+        let got = lexer.scan().unwrap();
 
-        assert_eq!(count, count_should_be);
-        assert_eq!(identifiers, identifiers_should_be);
+        assert_eq!(got.command.count, Some(123));
+        assert_eq!(got.command.name, "hello");
+        assert!(got.command.args.is_empty());
+        assert_eq!(got.name, Span::new(4, 9));
     }
 
     #[test]
@@ -266,6 +606,124 @@ mod tests {
         assert_eq!(got, should_be);
     }
 
+    #[test]
+    fn arguments_can_contain_any_non_whitespace() {
+        let inputs = vec![("komi 6.5", vec!["6.5"]),
+                          ("loadsgf game-1.sgf 42", vec!["game-1.sgf", "42"]),
+                          ("custom a=b c/d e,f", vec!["a=b", "c/d", "e,f"]),
+                          ("name_with_unicode 囲碁 ☗", vec!["囲碁", "☗"])];
+
+        for (src, args) in inputs {
+            let got = Parser::new(src).parse().unwrap();
+            assert_eq!(got.args, args);
+        }
+    }
+
+    #[test]
+    fn the_entire_line_is_consumed() {
+        let src = "12 play (black) [D5]\u{a0}{x} \n";
+        let mut lexer = Scanner::new(src);
+
+        lexer.skip_optional_whitespace();
+        assert_eq!(lexer.read_number().unwrap(), Some(12));
+
+        let mut tokens = vec![];
+        lexer.skip_whitespace().unwrap();
+        while let Some(token) = lexer.lex_identifier() {
+            tokens.push(token);
+            lexer.skip_optional_whitespace();
+        }
+
+        assert_eq!(tokens, vec!["play", "(black)", "[D5]\u{a0}{x}"]);
+        assert_eq!(lexer.pointer, src.len());
+    }
+
+    #[test]
+    fn preprocess_strips_comments_and_control_characters() {
+        let inputs = vec![("play black D5", Some("play black D5")),
+                          ("1 genmove black # think hard", Some("1 genmove black ")),
+                          ("play\tblack\tD5", Some("play black D5")),
+                          ("pl\x07ay\r black\x7f D5\n", Some("play black D5\n")),
+                          ("", None),
+                          ("   \t  \n", None),
+                          ("# a comment", None),
+                          ("\x01\x02\r\n", None)];
+
+        for (src, should_be) in inputs {
+            let got = preprocess(src);
+            assert_eq!(got, should_be.map(|s| s.to_string()), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn parse_with_comments_and_tabs() {
+        let src = "1 genmove\tblack # think hard\n";
+        let should_be = RawCommand {
+            count: Some(1),
+            name: "genmove".to_string(),
+            args: vec!["black".to_string()],
+        };
+
+        let got = Parser::new(src).parse().unwrap();
+
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn lines_to_ignore_are_distinct_from_missing_commands() {
+        for src in &["", "  \t ", "# nothing to see here"] {
+            match *Parser::new(src).parse().unwrap_err().kind() {
+                ErrorKind::EmptyLine => {}
+                ref other => panic!("Expected EmptyLine for {:?}, got {:?}", src, other),
+            }
+        }
+
+        match *Parser::new("42 ").parse().unwrap_err().kind() {
+            ErrorKind::NoCommand => {}
+            ref other => panic!("Expected NoCommand, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn huge_ids_are_an_error() {
+        let err = Parser::new("4294967296 name").parse().unwrap_err();
+
+        assert_eq!(*err.kind(), ErrorKind::IdOverflow("4294967296".to_string()));
+    }
+
+    #[test]
+    fn typed_arguments() {
+        use crate::values::{Color, Int, Vertex};
+
+        let cmd: RawCommand = parse("play w Q16 19 black").unwrap();
+
+        assert_eq!(cmd.arg::<Color>(0).unwrap(), Color::White);
+        assert_eq!(cmd.arg::<Vertex>(1).unwrap(),
+                   Vertex::Point { column: 15, row: 15 });
+        assert_eq!(cmd.arg::<Int>(2).unwrap(), Int(19));
+        assert_eq!(cmd.arg::<u32>(2).unwrap(), 19);
+        assert_eq!(cmd.move_arg(0).unwrap(),
+                   Move::new(Color::White, Vertex::Point { column: 15, row: 15 }));
+
+        match *cmd.arg::<Vertex>(0).unwrap_err().kind() {
+            ErrorKind::InvalidArgument(0, ref arg) if arg == "w" => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+        match *cmd.arg::<Color>(4).unwrap_err().kind() {
+            ErrorKind::MissingArgument(4) => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+        assert!(cmd.move_arg(3).is_err());
+    }
+
+    #[test]
+    fn raw_commands_round_trip() {
+        for src in &["3 play black D5", "quit", "loadsgf game-1.sgf 4"] {
+            let got: RawCommand = parse(src).unwrap();
+            assert_eq!(got.to_string(), *src);
+        }
+    }
+
     #[test]
     fn parse_into_any_type() {
         // Here we define some custom type which can be converted from a
@@ -289,4 +747,63 @@ mod tests {
 
         assert_eq!(got, should_be);
     }
+
+    #[test]
+    fn borrowed_parsing_matches_owned_parsing() {
+        let inputs = vec!["3 play black D5",
+                          "quit",
+                          "  1 genmove\tblack # think hard\r\n",
+                          "loadsgf game-1.sgf 42\n",
+                          "custom a b c d e f g",
+                          "12 play (black) [D5]\u{a0}{x} \n"];
+
+        for src in inputs {
+            let owned: RawCommand = parse(src).unwrap();
+            let borrowed = parse_borrowed(src).unwrap();
+
+            assert_eq!(RawCommandRef::from(&owned), borrowed, "{:?}", src);
+            assert_eq!(borrowed.to_string(), owned.to_string());
+            assert_eq!(RawCommand::from(borrowed), owned);
+        }
+    }
+
+    #[test]
+    fn borrowed_parsing_errors() {
+        let inputs = vec![("", ErrorKind::EmptyLine),
+                          ("  # just a comment\r\n", ErrorKind::EmptyLine),
+                          ("42 ", ErrorKind::NoCommand),
+                          ("12play", ErrorKind::NoWhitespace),
+                          ("4294967296 name", ErrorKind::IdOverflow("4294967296".to_string())),
+                          ("pl\x07ay", ErrorKind::ControlCharacter(2))];
+
+        for (src, should_be) in inputs {
+            let got = parse_borrowed(src).unwrap_err();
+            assert_eq!(got.kind().to_string(), should_be.to_string(), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn borrowed_and_owned_parsing_fail_the_same_way() {
+        let inputs = vec!["", "   \t ", "# a comment", "42", "42 ", "42\t# comment",
+                          "12play", "4294967296 name", "007x genmove black"];
+
+        for src in inputs {
+            let owned = Parser::new(src).parse().unwrap_err();
+            let borrowed = parse_borrowed(src).unwrap_err();
+
+            assert_eq!(owned.kind(), borrowed.kind(), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn borrowed_typed_arguments() {
+        use crate::values::{Color, Vertex};
+
+        let cmd = parse_borrowed("play w Q16").unwrap();
+
+        assert_eq!(cmd.arg::<Color>(0).unwrap(), Color::White);
+        assert_eq!(cmd.arg::<Vertex>(1).unwrap(),
+                   Vertex::Point { column: 15, row: 15 });
+        assert!(cmd.arg::<Color>(2).is_err());
+    }
 }
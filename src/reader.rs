@@ -0,0 +1,139 @@
+//! Reading a whole session's worth of commands from a stream.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::io::Cursor;
+//! use go_text_protocol::CommandReader;
+//!
+//! let session = Cursor::new("1 boardsize 9\r\n# set up the board\n\n2 play b E5\n3 genmove w");
+//! let names: Vec<String> = CommandReader::new(session)
+//!     .map(|cmd| cmd.unwrap().name)
+//!     .collect();
+//!
+//! assert_eq!(names, vec!["boardsize", "play", "genmove"]);
+//! ```
+
+use std::borrow::Cow;
+use std::io::BufRead;
+
+use crate::errors::*;
+use crate::parser::{Parser, RawCommand};
+
+/// An iterator which reads commands from a `BufRead` stream, one line at a
+/// time.
+///
+/// Each line is preprocessed, so blank lines and comments are skipped and
+/// `CRLF` line endings are fine. The last line doesn't need a trailing
+/// newline.
+///
+/// If a line can't be parsed you'll get an `ErrorKind::Line` error (with the
+/// parse error as its cause) and the iterator carries on with the next line.
+/// I/O errors are passed through as-is.
+#[derive(Debug)]
+pub struct CommandReader<R> {
+    reader: R,
+    line_number: usize,
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead> CommandReader<R> {
+    /// Create a new `CommandReader`.
+    pub fn new(reader: R) -> CommandReader<R> {
+        CommandReader {
+            reader,
+            line_number: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// The (1-based) number of the last line read, or `0` if nothing has been
+    /// read yet.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// The last line read (lossily converted to UTF-8), handy for pointing
+    /// at the problem with a `diagnostic::Diagnostic` when a line can't be
+    /// parsed.
+    pub fn last_line(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.buffer)
+    }
+
+    /// Get back the underlying stream.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for CommandReader<R> {
+    type Item = Result<RawCommand>;
+
+    fn next(&mut self) -> Option<Result<RawCommand>> {
+        loop {
+            self.buffer.clear();
+
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => self.line_number += 1,
+                Err(e) => return Some(Err(e.into())),
+            }
+
+            let line = String::from_utf8_lossy(&self.buffer);
+
+            match Parser::new(&line).parse() {
+                Ok(cmd) => return Some(Ok(cmd)),
+                Err(ref e) if *e.kind() == ErrorKind::EmptyLine => continue,
+                Err(e) => {
+                    let line_number = self.line_number;
+                    return Some(Err(e).chain_err(|| ErrorKind::Line(line_number)));
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn read_a_session() {
+        let src = "1 name\r\n\r\n  # just a comment\n\t2 play\tblack D4 # hi\r\n\x01\n3 quit";
+        let mut reader = CommandReader::new(Cursor::new(src));
+
+        let got: Vec<_> = reader.by_ref().map(|cmd| cmd.unwrap().to_string()).collect();
+
+        assert_eq!(got, vec!["1 name", "2 play black D4", "3 quit"]);
+        assert_eq!(reader.line_number(), 6);
+    }
+
+    #[test]
+    fn errors_include_the_line_number() {
+        let src = "name\n\n42\nversion\n";
+        let mut reader = CommandReader::new(Cursor::new(src));
+
+        assert_eq!(reader.next().unwrap().unwrap().name, "name");
+
+        match *reader.next().unwrap().unwrap_err().kind() {
+            ErrorKind::Line(3) => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+        assert_eq!(reader.last_line(), "42\n");
+
+        // and we can keep going afterwards
+        assert_eq!(reader.next().unwrap().unwrap().name, "version");
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced() {
+        let src: &[u8] = b"play b \xff\n";
+
+        let got = CommandReader::new(src).next().unwrap().unwrap();
+
+        assert_eq!(got.args, vec!["b", "\u{fffd}"]);
+    }
+}
@@ -0,0 +1,430 @@
+//! Running GNU Go style regression tests.
+//!
+//! A regression file (usually ending in `.tst`) is a list of GTP commands.
+//! Any numbered command followed by a `#?` comment is a test, and the
+//! comment says which answers are correct:
+//!
+//! ```text
+//! loadsgf games/trevor/auto/a19.sgf 14
+//! 1 reg_genmove white
+//! #? [D5|E5]
+//!
+//! # anything except passing, but we don't expect to get this right yet
+//! 2 reg_genmove black
+//! #? [!PASS]*
+//! ```
+//!
+//! A pattern is a list of alternatives separated by `|`, which may be
+//! grouped with parentheses (e.g. `[1 (C3|D3)]`). Answers are compared
+//! ignoring case and extra whitespace. A leading `!` means any answer
+//! *except* those is correct, and a trailing `*` marks a test which is
+//! expected to fail.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use go_text_protocol::regression::{self, Outcome};
+//!
+//! let steps = regression::parse_tests("boardsize 9\n1 reg_genmove black\n#? [E5|D4]*\n")
+//!     .unwrap();
+//!
+//! assert_eq!(steps.len(), 2);
+//! let expected = steps[1].expected.as_ref().unwrap();
+//!
+//! assert_eq!(expected.outcome("e5"), Outcome::UnexpectedPass);
+//! assert_eq!(expected.outcome("A1"), Outcome::ExpectedFailure);
+//! ```
+
+use std::fmt::{self, Display, Formatter};
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use crate::controller::Controller;
+use crate::errors::*;
+use crate::parser::{self, RawCommand};
+
+/// The answers a test will accept.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Expected {
+    /// The pattern, as written between the square brackets (without `!`).
+    pub pattern: String,
+    /// Every answer the pattern matches, normalised to upper case with
+    /// single spaces.
+    pub alternatives: Vec<String>,
+    /// Is any answer *except* the alternatives correct (`!`)?
+    pub negated: bool,
+    /// Is the test expected to fail (`*`)?
+    pub expect_failure: bool,
+}
+
+impl Expected {
+    /// Is `answer` correct?
+    pub fn matches(&self, answer: &str) -> bool {
+        let answer = normalise(answer);
+        self.alternatives.contains(&answer) != self.negated
+    }
+
+    /// Work out how a test went if the engine gave this answer.
+    pub fn outcome(&self, answer: &str) -> Outcome {
+        match (self.matches(answer), self.expect_failure) {
+            (true, false) => Outcome::Passed,
+            (false, false) => Outcome::Failed,
+            (true, true) => Outcome::UnexpectedPass,
+            (false, true) => Outcome::ExpectedFailure,
+        }
+    }
+}
+
+impl FromStr for Expected {
+    type Err = Error;
+
+    /// Parse the part of a `#?` comment after the `#?`, e.g. `[!PASS]*`.
+    fn from_str(s: &str) -> Result<Expected> {
+        let invalid = || Error::from(ErrorKind::InvalidValue("expected answer", s.to_string()));
+        let s = s.trim();
+
+        let (inside, suffix) = match (s.strip_prefix('['), s.rfind(']')) {
+            (Some(_), Some(end)) => (&s[1..end], s[end + 1..].trim()),
+            _ => return Err(invalid()),
+        };
+
+        let expect_failure = match suffix {
+            "" => false,
+            "*" => true,
+            _ => return Err(invalid()),
+        };
+
+        let (negated, pattern) = match inside.strip_prefix('!') {
+            Some(rest) => (true, rest.trim()),
+            None => (false, inside.trim()),
+        };
+
+        let mut chars = pattern.chars().peekable();
+        let alternatives = expand(&mut chars).ok_or_else(invalid)?;
+        if chars.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Expected {
+            pattern: pattern.to_string(),
+            alternatives: alternatives.iter().map(|alt| normalise(alt)).collect(),
+            negated,
+            expect_failure,
+        })
+    }
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let bang = if self.negated { "!" } else { "" };
+        let star = if self.expect_failure { "*" } else { "" };
+        write!(f, "[{}{}]{}", bang, self.pattern, star)
+    }
+}
+
+/// Expand a pattern into every string it matches, stopping at an unmatched
+/// `)`. Returns `None` if a group isn't closed.
+fn expand<I>(chars: &mut ::std::iter::Peekable<I>) -> Option<Vec<String>>
+    where I: Iterator<Item = char>
+{
+    let mut alternatives = Vec::new();
+    let mut current = vec![String::new()];
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            ')' => break,
+            '|' => {
+                chars.next();
+                alternatives.append(&mut current);
+                current.push(String::new());
+            }
+            '(' => {
+                chars.next();
+                let group = expand(chars)?;
+                if chars.next() != Some(')') {
+                    return None;
+                }
+
+                current = current.iter()
+                    .flat_map(|prefix| group.iter().map(move |g| format!("{}{}", prefix, g)))
+                    .collect();
+            }
+            _ => {
+                chars.next();
+                for prefix in &mut current {
+                    prefix.push(c);
+                }
+            }
+        }
+    }
+
+    alternatives.append(&mut current);
+    Some(alternatives)
+}
+
+fn normalise(answer: &str) -> String {
+    answer.split_whitespace().collect::<Vec<_>>().join(" ").to_uppercase()
+}
+
+/// One command from a regression file.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Step {
+    /// The (1-based) line the command was on.
+    pub line_number: usize,
+    /// The command to send to the engine.
+    pub command: RawCommand,
+    /// The answers to accept, if this command is a test.
+    pub expected: Option<Expected>,
+}
+
+/// Parse the contents of a regression file.
+///
+/// It's an error for a `#?` comment to follow anything other than a
+/// numbered command, and any errors are wrapped in an `ErrorKind::Line`.
+pub fn parse_tests(src: &str) -> Result<Vec<Step>> {
+    let mut steps: Vec<Step> = Vec::new();
+
+    for (i, line) in src.lines().enumerate() {
+        let line_number = i + 1;
+
+        if let Some(expected) = line.trim_start().strip_prefix("#?") {
+            let step = match steps.last_mut() {
+                Some(step) if step.command.count.is_some() && step.expected.is_none() => step,
+                _ => {
+                    let err = "An expected answer must follow a numbered command";
+                    return Err(Error::with_source(ErrorKind::Line(line_number), Error::from(err)));
+                }
+            };
+
+            step.expected = Some(expected.parse().chain_err(|| ErrorKind::Line(line_number))?);
+            continue;
+        }
+
+        match parser::parse::<RawCommand>(line) {
+            Ok(command) => {
+                steps.push(Step {
+                    line_number,
+                    command,
+                    expected: None,
+                })
+            }
+            Err(ref e) if *e.kind() == ErrorKind::EmptyLine => {}
+            Err(e) => return Err(Error::with_source(ErrorKind::Line(line_number), e)),
+        }
+    }
+
+    Ok(steps)
+}
+
+/// How a single test went.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Outcome {
+    /// The answer was correct.
+    Passed,
+    /// The answer was wrong.
+    Failed,
+    /// The answer was wrong, but the test was expected to fail.
+    ExpectedFailure,
+    /// The answer was correct, even though the test was expected to fail.
+    UnexpectedPass,
+}
+
+/// The result of one test.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TestResult {
+    /// The test's number.
+    pub id: u32,
+    /// The line the test's command was on.
+    pub line_number: usize,
+    /// The answers the test would accept.
+    pub expected: Expected,
+    /// What the engine said, with failures written as `? message`.
+    pub answer: String,
+    /// How it went.
+    pub outcome: Outcome,
+}
+
+impl Display for TestResult {
+    /// Describe the result the same way GNU Go's regression scripts do.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.outcome {
+            Outcome::Passed => write!(f, "{} passed", self.id),
+            Outcome::ExpectedFailure => write!(f, "{} failed as expected", self.id),
+            Outcome::UnexpectedPass => write!(f, "{} unexpected PASS!", self.id),
+            Outcome::Failed => {
+                write!(f, "{} FAILED: Correct '{}', got '{}'", self.id, self.expected, self.answer)
+            }
+        }
+    }
+}
+
+/// The results of running a regression file.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Summary {
+    /// Every test which was run, in order.
+    pub results: Vec<TestResult>,
+}
+
+impl Summary {
+    /// How many tests had a particular outcome?
+    pub fn count(&self, outcome: Outcome) -> usize {
+        self.results.iter().filter(|r| r.outcome == outcome).count()
+    }
+
+    /// Did everything go as expected (no failures or unexpected passes)?
+    pub fn is_success(&self) -> bool {
+        self.count(Outcome::Failed) == 0 && self.count(Outcome::UnexpectedPass) == 0
+    }
+
+    /// Every test which didn't pass or fail as expected.
+    pub fn surprises(&self) -> impl Iterator<Item = &TestResult> {
+        self.results.iter().filter(|r| {
+            r.outcome == Outcome::Failed || r.outcome == Outcome::UnexpectedPass
+        })
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f,
+               "{} passed, {} failed, {} unexpected passes, {} expected failures",
+               self.count(Outcome::Passed),
+               self.count(Outcome::Failed),
+               self.count(Outcome::UnexpectedPass),
+               self.count(Outcome::ExpectedFailure))
+    }
+}
+
+/// Send every step to an engine, checking the answers to any tests.
+///
+/// Setup commands which fail (e.g. a `loadsgf` with a missing file) are
+/// ignored, just like GNU Go's scripts do, so only communication errors
+/// stop the run. A test whose command fails never passes, whatever the
+/// pattern says, so it counts as a failure (or an expected failure).
+pub fn run_tests<W, R>(controller: &mut Controller<W, R>, steps: &[Step]) -> Result<Summary>
+    where W: Write,
+          R: BufRead
+{
+    let mut summary = Summary::default();
+
+    for step in steps {
+        // the controller picks its own ids
+        let command = RawCommand {
+            count: None,
+            ..step.command.clone()
+        };
+        let response = controller.send(&command)?;
+
+        let (expected, id) = match (&step.expected, step.command.count) {
+            (Some(expected), Some(id)) => (expected, id),
+            _ => continue,
+        };
+
+        let (answer, outcome) = if response.is_success() {
+            let answer = response.payload.trim().to_string();
+            let outcome = expected.outcome(&answer);
+            (answer, outcome)
+        } else if expected.expect_failure {
+            (format!("? {}", response.payload.trim()), Outcome::ExpectedFailure)
+        } else {
+            (format!("? {}", response.payload.trim()), Outcome::Failed)
+        };
+
+        summary.results.push(TestResult {
+            id,
+            line_number: step.line_number,
+            expected: expected.clone(),
+            outcome,
+            answer,
+        });
+    }
+
+    Ok(summary)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::{Response, write_response};
+
+    #[test]
+    fn parse_expected_answers() {
+        let got: Expected = " [!(A1|B2) pass|C3]* ".parse().unwrap();
+
+        assert!(got.negated);
+        assert!(got.expect_failure);
+        assert_eq!(got.alternatives, vec!["A1 PASS", "B2 PASS", "C3"]);
+        assert_eq!(got.to_string(), "[!(A1|B2) pass|C3]*");
+
+        assert!(got.matches("D4"));
+        assert!(!got.matches("b2   Pass"));
+
+        for src in &["", "D5", "[D5", "[D5]?", "[(D5]", "[D5)]"] {
+            assert!(src.parse::<Expected>().is_err(), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn nested_groups() {
+        let got: Expected = "[1 (C3|D(3|4))]".parse().unwrap();
+
+        assert_eq!(got.alternatives, vec!["1 C3", "1 D3", "1 D4"]);
+    }
+
+    #[test]
+    fn parse_a_regression_file() {
+        let src = "# setting things up\nboardsize 9\n\n1 genmove black\n#? [E5]\n2 name\n";
+
+        let got = parse_tests(src).unwrap();
+
+        assert_eq!(got.len(), 3);
+        assert_eq!(got[1].line_number, 4);
+        assert_eq!(got[1].expected.as_ref().unwrap().alternatives, vec!["E5"]);
+        assert_eq!(got[2].command.count, Some(2));
+        assert!(got[2].expected.is_none());
+    }
+
+    #[test]
+    fn misplaced_expected_answers() {
+        for src in &["#? [E5]", "boardsize 9\n#? [E5]", "1 name\n#? [a]\n#? [b]", "1 name\n#? E5"] {
+            let err = parse_tests(src).unwrap_err();
+            assert!(matches!(*err.kind(), ErrorKind::Line(_)), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn run_against_canned_responses() {
+        let steps = parse_tests("boardsize 9\n1 genmove black\n#? [E5]\n2 genmove white\n#? \
+                                 [C3]*\n3 play white Z1\n#? [!]\n4 genmove black\n#? [D4]*\n\
+                                 5 play white Z2\n#? [!D4]*")
+            .unwrap();
+
+        let mut canned = Vec::new();
+        let responses = vec![Response::success(Some(1), ""),
+                             Response::success(Some(2), "E5"),
+                             Response::success(Some(3), "C3"),
+                             Response::failure(Some(4), "illegal move"),
+                             Response::success(Some(5), "G7"),
+                             Response::failure(Some(6), "illegal move")];
+        for response in &responses {
+            write_response(&mut canned, response).unwrap();
+        }
+
+        let mut controller = Controller::new(Vec::new(), canned.as_slice());
+        let summary = run_tests(&mut controller, &steps).unwrap();
+
+        let outcomes: Vec<Outcome> = summary.results.iter().map(|r| r.outcome).collect();
+        // an error never matches a pattern, even a negated one
+        assert_eq!(outcomes,
+                   vec![Outcome::Passed,
+                        Outcome::UnexpectedPass,
+                        Outcome::Failed,
+                        Outcome::ExpectedFailure,
+                        Outcome::ExpectedFailure]);
+        assert_eq!(summary.results[2].answer, "? illegal move");
+        assert!(!summary.is_success());
+        assert_eq!(summary.to_string(),
+                   "1 passed, 1 failed, 1 unexpected passes, 2 expected failures");
+    }
+}
@@ -0,0 +1,467 @@
+//! Responses sent from an engine back to the controller.
+//!
+//! A response starts with either `=` (success) or `?` (failure), immediately
+//! followed by the id of the command it is responding to (if there was one),
+//! then the response payload. The response is terminated by two consecutive
+//! newlines.
+//!
+//! ```text
+//! =3 D4
+//!
+//! ? unknown command
+//!
+//! =4 boardsize
+//! play
+//! genmove
+//!
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust
+//! use go_text_protocol::{Response, Vertex};
+//!
+//! let response: Response = "=3 D4\n\n".parse().unwrap();
+//!
+//! assert_eq!(response.id, Some(3));
+//! assert!(response.is_success());
+//! assert_eq!(response.value::<Vertex>().unwrap(),
+//!            Vertex::Point { column: 3, row: 3 });
+//! ```
+//!
+//! When talking to an engine you'll typically want to use `read_response()`
+//! to pull the next response off a stream.
+//!
+//! Going the other way, an engine can build a `Response` for the command it
+//! was sent and write it out. The `Display` impl produces exactly what the
+//! spec expects, including the terminating blank line.
+//!
+//! ```rust
+//! use go_text_protocol::{RawCommand, Response, parse};
+//!
+//! let cmd: RawCommand = parse("7 genmove black").unwrap();
+//! let ok: Result<_, String> = Ok("D4");
+//!
+//! let response = Response::reply_to(&cmd, ok);
+//! assert_eq!(response.to_string(), "=7 D4\n\n");
+//! ```
+
+use std::fmt::{self, Display, Formatter};
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use crate::errors::*;
+use crate::parser::RawCommand;
+
+/// Whether the engine was able to carry out a command.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Status {
+    /// The command succeeded (`=`).
+    Success,
+    /// The command failed (`?`), the payload contains an error message.
+    Failure,
+}
+
+/// A response from the engine.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Response {
+    /// The id of the command being responded to, if it had one.
+    pub id: Option<u32>,
+
+    /// Whether the command succeeded or failed.
+    pub status: Status,
+
+    /// The body of the response. Multi-line responses are joined with `\n`,
+    /// and the terminating blank line isn't included.
+    pub payload: String,
+}
+
+impl Response {
+    /// Create a successful response.
+    pub fn success<S: Into<String>>(id: Option<u32>, payload: S) -> Response {
+        Response {
+            id,
+            status: Status::Success,
+            payload: payload.into(),
+        }
+    }
+
+    /// Create a failure response with the provided error message.
+    pub fn failure<S: Into<String>>(id: Option<u32>, message: S) -> Response {
+        Response {
+            id,
+            status: Status::Failure,
+            payload: message.into(),
+        }
+    }
+
+    /// Create a response from the result of carrying out a command with the
+    /// provided id.
+    pub fn from_result<T, E>(id: Option<u32>, result: ::std::result::Result<T, E>) -> Response
+        where T: Display,
+              E: Display
+    {
+        match result {
+            Ok(value) => Response::success(id, value.to_string()),
+            Err(e) => Response::failure(id, e.to_string()),
+        }
+    }
+
+    /// Create a response to a particular command, making sure its id is
+    /// echoed back.
+    pub fn reply_to<T, E>(command: &RawCommand, result: ::std::result::Result<T, E>) -> Response
+        where T: Display,
+              E: Display
+    {
+        Response::from_result(command.count, result)
+    }
+
+    /// Did the command succeed?
+    pub fn is_success(&self) -> bool {
+        self.status == Status::Success
+    }
+
+    /// Iterate over the individual lines in the payload.
+    pub fn lines(&self) -> ::std::str::Lines<'_> {
+        self.payload.lines()
+    }
+
+    /// Get the payload, turning a failure response into an error.
+    pub fn into_result(self) -> Result<String> {
+        match self.status {
+            Status::Success => Ok(self.payload),
+            Status::Failure => Err(ErrorKind::ResponseFailure(self.payload).into()),
+        }
+    }
+
+    /// Parse the payload of a successful response as a single value (usually
+    /// one of the types in the `values` module).
+    pub fn value<T>(&self) -> Result<T>
+        where T: FromStr,
+              T::Err: ::std::error::Error + Send + Sync + 'static
+    {
+        self.check_success()?;
+        let payload = self.payload.trim();
+
+        payload.parse()
+            .chain_err(|| ErrorKind::MalformedResponse(payload.to_string()))
+    }
+
+    /// Parse the payload of a successful response as a whitespace-separated
+    /// list of values (e.g. the vertices returned by `fixed_handicap`).
+    pub fn values<T>(&self) -> Result<Vec<T>>
+        where T: FromStr,
+              T::Err: ::std::error::Error + Send + Sync + 'static
+    {
+        self.check_success()?;
+
+        self.payload
+            .split_whitespace()
+            .map(|word| {
+                word.parse()
+                    .chain_err(|| ErrorKind::MalformedResponse(word.to_string()))
+            })
+            .collect()
+    }
+
+    fn check_success(&self) -> Result<()> {
+        match self.status {
+            Status::Success => Ok(()),
+            Status::Failure => Err(ErrorKind::ResponseFailure(self.payload.clone()).into()),
+        }
+    }
+}
+
+impl Display for Response {
+    /// Write the response in its wire format.
+    ///
+    /// Any trailing newlines in the payload are dropped and empty lines are
+    /// replaced with a single space, so the only blank line written is the
+    /// one terminating the response.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.status {
+            Status::Success => write!(f, "=")?,
+            Status::Failure => write!(f, "?")?,
+        }
+
+        if let Some(id) = self.id {
+            write!(f, "{}", id)?;
+        }
+
+        let payload = self.payload.trim_end_matches(['\r', '\n']);
+
+        if !payload.is_empty() {
+            write!(f, " ")?;
+
+            for (i, line) in payload.split('\n').enumerate() {
+                let line = line.trim_end_matches('\r');
+
+                if i > 0 {
+                    writeln!(f)?;
+                    if line.is_empty() {
+                        write!(f, " ")?;
+                    }
+                }
+                write!(f, "{}", line)?;
+            }
+        }
+
+        write!(f, "\n\n")
+    }
+}
+
+/// Write a response to the controller and flush the stream.
+pub fn write_response<W: Write>(writer: &mut W, response: &Response) -> Result<()> {
+    write!(writer, "{}", response)?;
+    writer.flush()?;
+    Ok(())
+}
+
+impl FromStr for Response {
+    type Err = Error;
+
+    /// Parse a single response.
+    ///
+    /// Leading blank lines and carriage returns are ignored, and the
+    /// terminating blank line is optional, but it's an error for anything to
+    /// come after it.
+    fn from_str(src: &str) -> Result<Response> {
+        let mut lines = src.split('\n')
+            .map(|line| line.trim_end_matches('\r'))
+            .skip_while(|line| line.is_empty());
+
+        let first = match lines.next() {
+            Some(line) => line,
+            None => bail!(ErrorKind::IncompleteResponse),
+        };
+        let (id, status, first_line) = parse_header(first)?;
+
+        let mut payload = first_line.to_string();
+
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+            payload.push('\n');
+            payload.push_str(line);
+        }
+
+        if let Some(extra) = lines.find(|line| !line.is_empty()) {
+            bail!(ErrorKind::MalformedResponse(extra.to_string()));
+        }
+
+        Ok(Response {
+               id,
+               status,
+               payload,
+           })
+    }
+}
+
+/// Split the first line of a response into its id, status, and the start of
+/// the payload.
+fn parse_header(line: &str) -> Result<(Option<u32>, Status, &str)> {
+    let status = match line.chars().next() {
+        Some('=') => Status::Success,
+        Some('?') => Status::Failure,
+        _ => bail!(ErrorKind::MalformedResponse(line.to_string())),
+    };
+
+    let rest = &line[1..];
+    let digits = rest.bytes().take_while(|b| b.is_ascii_digit()).count();
+
+    let id = if digits > 0 {
+        let id = rest[..digits].parse()
+            .chain_err(|| ErrorKind::MalformedResponse(line.to_string()))?;
+        Some(id)
+    } else {
+        None
+    };
+
+    let rest = &rest[digits..];
+
+    let payload = if rest.is_empty() {
+        rest
+    } else if rest.starts_with(' ') || rest.starts_with('\t') {
+        &rest[1..]
+    } else {
+        bail!(ErrorKind::MalformedResponse(line.to_string()));
+    };
+
+    Ok((id, status, payload))
+}
+
+/// Read the next response from a stream, consuming everything up to and
+/// including its terminating blank line.
+///
+/// ```rust
+/// use std::io::Cursor;
+/// use go_text_protocol::response::read_response;
+///
+/// let mut stream = Cursor::new("=1\n\n?2 unknown command\n\n");
+///
+/// let first = read_response(&mut stream).unwrap();
+/// assert_eq!(first.id, Some(1));
+/// assert_eq!(first.payload, "");
+///
+/// let second = read_response(&mut stream).unwrap();
+/// assert!(!second.is_success());
+/// assert_eq!(second.payload, "unknown command");
+/// ```
+pub fn read_response<R: BufRead>(reader: &mut R) -> Result<Response> {
+    let mut buffer = String::new();
+
+    loop {
+        let start = buffer.len();
+        let bytes_read = reader.read_line(&mut buffer)?;
+
+        if bytes_read == 0 {
+            bail!(ErrorKind::IncompleteResponse);
+        }
+
+        let is_blank = buffer[start..].trim_end_matches(['\r', '\n']).is_empty();
+
+        if is_blank {
+            if buffer.trim().is_empty() {
+                // we haven't seen the start of a response yet
+                buffer.clear();
+            } else {
+                return buffer.parse();
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::values::{Color, Move, Vertex};
+
+    #[test]
+    fn parse_simple_responses() {
+        let inputs = vec![("=3 D4\n\n", Some(3), Status::Success, "D4"),
+                          ("= D4\n\n", None, Status::Success, "D4"),
+                          ("=\n\n", None, Status::Success, ""),
+                          ("=42\n\n", Some(42), Status::Success, ""),
+                          ("? unknown command\n\n", None, Status::Failure, "unknown command"),
+                          ("?7 illegal move\r\n\r\n", Some(7), Status::Failure, "illegal move"),
+                          ("\n\n=1 pass", Some(1), Status::Success, "pass")];
+
+        for (src, id, status, payload) in inputs {
+            let got: Response = src.parse().unwrap();
+            let should_be = Response {
+                id,
+                status,
+                payload: payload.to_string(),
+            };
+            assert_eq!(got, should_be, "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn parse_multi_line_response() {
+        let src = "=12 boardsize\nplay\n  genmove\n\n";
+
+        let got: Response = src.parse().unwrap();
+
+        assert_eq!(got.id, Some(12));
+        assert_eq!(got.lines().collect::<Vec<_>>(),
+                   vec!["boardsize", "play", "  genmove"]);
+    }
+
+    #[test]
+    fn invalid_responses() {
+        let inputs = vec!["", "\n\n", "! what", "=D4\n\n", "=99999999999 pass\n\n",
+                          "=1\n\n=2\n\n"];
+
+        for src in inputs {
+            assert!(src.parse::<Response>().is_err(), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn typed_payloads() {
+        let response: Response = "= D4 Q16 pass\n\n".parse().unwrap();
+        assert_eq!(response.values::<Vertex>().unwrap(),
+                   vec![Vertex::Point { column: 3, row: 3 },
+                        Vertex::Point { column: 15, row: 15 },
+                        Vertex::Pass]);
+        assert!(response.value::<Vertex>().is_err());
+
+        let response: Response = "= white C3\n\n".parse().unwrap();
+        assert_eq!(response.value::<Move>().unwrap(),
+                   Move::new(Color::White, Vertex::Point { column: 2, row: 2 }));
+
+        let response: Response = "? illegal move\n\n".parse().unwrap();
+        match *response.value::<Vertex>().unwrap_err().kind() {
+            ErrorKind::ResponseFailure(ref msg) if msg == "illegal move" => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn format_responses() {
+        let inputs = vec![(Response::success(Some(3), "D4"), "=3 D4\n\n"),
+                          (Response::success(None, "D4"), "= D4\n\n"),
+                          (Response::success(Some(1), ""), "=1\n\n"),
+                          (Response::success(None, ""), "=\n\n"),
+                          (Response::failure(Some(2), "cannot undo"), "?2 cannot undo\n\n"),
+                          (Response::failure(None, "unknown command"), "? unknown command\n\n"),
+                          (Response::success(Some(4), "a\nb\n"), "=4 a\nb\n\n"),
+                          (Response::success(None, "a\n\n\nb\n\n"), "= a\n \n \nb\n\n"),
+                          (Response::success(None, "\r\na\r\n\r\nb"), "= \na\n \nb\n\n"),
+                          (Response::success(None, "\nboard"), "= \nboard\n\n")];
+
+        for (response, should_be) in inputs {
+            assert_eq!(response.to_string(), should_be, "{:?}", response);
+        }
+    }
+
+    #[test]
+    fn formatted_responses_round_trip() {
+        let inputs = vec![Response::success(Some(3), "D4"),
+                          Response::failure(None, "illegal move"),
+                          Response::success(Some(9), ""),
+                          Response::success(Some(10), "\n   A B C\n 3 . . .\n 2 . X .")];
+
+        for response in inputs {
+            let got: Response = response.to_string().parse().unwrap();
+            assert_eq!(got, response);
+        }
+
+        // blank lines get normalised, but at least it's still one response
+        let got: Response = Response::success(None, "a\n\nb").to_string().parse().unwrap();
+        assert_eq!(got.payload, "a\n \nb");
+    }
+
+    #[test]
+    fn reply_to_a_command() {
+        let cmd: RawCommand = crate::parser::parse("42 play black Z99").unwrap();
+        let result: ::std::result::Result<&str, &str> = Err("illegal move");
+
+        let got = Response::reply_to(&cmd, result);
+
+        assert_eq!(got, Response::failure(Some(42), "illegal move"));
+
+        let mut buffer = Vec::new();
+        write_response(&mut buffer, &got).unwrap();
+        assert_eq!(buffer, b"?42 illegal move\n\n");
+    }
+
+    #[test]
+    fn read_several_responses_from_a_stream() {
+        let src = "=1 D4\n\n\n?2 cannot undo\n\n=3 a\nb\n\n=4";
+        let mut stream = Cursor::new(src);
+
+        assert_eq!(read_response(&mut stream).unwrap().payload, "D4");
+        assert_eq!(read_response(&mut stream).unwrap().id, Some(2));
+        assert_eq!(read_response(&mut stream).unwrap().payload, "a\nb");
+
+        match *read_response(&mut stream).unwrap_err().kind() {
+            ErrorKind::IncompleteResponse => {}
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+}
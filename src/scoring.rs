@@ -0,0 +1,473 @@
+//! Working out who won a finished game.
+//!
+//! A `Scorer` takes the final position, which stones are dead, and the rules
+//! being used, and comes up with a `GameResult` in the format `final_score`
+//! uses. This lets a controller check the scores engines report.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use go_text_protocol::{Color, Move, StoneStatus};
+//! use go_text_protocol::board::Board;
+//! use go_text_protocol::scoring::{GameResult, Rules, Scorer};
+//!
+//! let mut board = Board::new(5).unwrap();
+//! for vertex in &["C1", "C2", "C3", "C4", "C5"] {
+//!     board.play(Move::new(Color::Black, vertex.parse().unwrap())).unwrap();
+//! }
+//! for vertex in &["D1", "D2", "D3", "D4", "D5", "A3"] {
+//!     board.play(Move::new(Color::White, vertex.parse().unwrap())).unwrap();
+//! }
+//!
+//! // the white stone on A3 doesn't stand a chance
+//! let mut scorer = Scorer::new(&board);
+//! scorer.mark_dead("A3".parse().unwrap());
+//! assert_eq!(scorer.status_list(StoneStatus::Dead), vec!["A3".parse().unwrap()]);
+//!
+//! // black has 15 points on the board, white has 10 plus komi
+//! let result = scorer.score(5.5, Rules::Area);
+//! assert_eq!(result, GameResult::Win(Color::White, 0.5));
+//! assert_eq!(result.to_string(), "W+0.5");
+//!
+//! // results reported by an engine can be parsed for comparison
+//! assert_eq!("W+0.5".parse::<GameResult>().unwrap(), result);
+//! ```
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::board::Board;
+use crate::errors::*;
+use crate::values::{Color, StoneStatus, Vertex};
+
+/// The ways of counting the score.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Rules {
+    /// Area (Chinese) scoring, where each player gets a point for every
+    /// stone they have on the board and every empty point they surround.
+    Area,
+    /// Territory (Japanese) scoring, where each player gets a point for
+    /// every empty point they surround and every prisoner they took.
+    Territory,
+}
+
+/// The result of a game, as reported by `final_score`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameResult {
+    /// A player won by some number of points (e.g. `B+3.5`).
+    Win(Color, f32),
+    /// A player won because their opponent resigned (e.g. `W+R`).
+    Resignation(Color),
+    /// Nobody won (`0`).
+    Draw,
+}
+
+impl GameResult {
+    /// The player who won, if there was one.
+    pub fn winner(&self) -> Option<Color> {
+        match *self {
+            GameResult::Win(color, _) | GameResult::Resignation(color) => Some(color),
+            GameResult::Draw => None,
+        }
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<GameResult> {
+        let invalid = || Error::from(ErrorKind::InvalidValue("game result", s.to_string()));
+
+        if s == "0" {
+            return Ok(GameResult::Draw);
+        }
+
+        let winner = match s.get(..2).map(|prefix| prefix.to_ascii_uppercase()) {
+            Some(ref prefix) if prefix == "B+" => Color::Black,
+            Some(ref prefix) if prefix == "W+" => Color::White,
+            _ => return Err(invalid()),
+        };
+
+        let rest = &s[2..];
+
+        if rest.eq_ignore_ascii_case("r") || rest.eq_ignore_ascii_case("resign") {
+            return Ok(GameResult::Resignation(winner));
+        }
+
+        match rest.parse::<f32>() {
+            Ok(margin) if margin > 0.0 && margin.is_finite() => Ok(GameResult::Win(winner, margin)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let letter = |color| match color {
+            Color::Black => 'B',
+            Color::White => 'W',
+        };
+
+        match *self {
+            GameResult::Win(color, margin) => write!(f, "{}+{}", letter(color), margin),
+            GameResult::Resignation(color) => write!(f, "{}+R", letter(color)),
+            GameResult::Draw => write!(f, "0"),
+        }
+    }
+}
+
+/// Scores the final position of a game, given which stones are dead.
+///
+/// Any stones which aren't marked as dead are alive. Seki detection is only
+/// a heuristic: groups are in seki when they share a region of one or two
+/// empty points with the opponent and none of them has an eye. Anything more
+/// complicated (like a seki where each side has an eye) shows up as alive.
+#[derive(Clone, Debug)]
+pub struct Scorer<'a> {
+    board: &'a Board,
+    dead: Vec<bool>,
+}
+
+impl<'a> Scorer<'a> {
+    /// Start scoring a position, with every stone alive.
+    pub fn new(board: &'a Board) -> Scorer<'a> {
+        let points = (board.size() * board.size()) as usize;
+
+        Scorer {
+            board,
+            dead: vec![false; points],
+        }
+    }
+
+    /// Mark the group of stones at `vertex` as dead, returning `false` if
+    /// there isn't a stone there.
+    pub fn mark_dead(&mut self, vertex: Vertex) -> bool {
+        let color = match self.board.get(vertex) {
+            Some(color) => color,
+            None => return false,
+        };
+        let start = self.board.index(vertex).expect("There's a stone on it");
+
+        for ix in self.chain(start, |point| point == Some(color)) {
+            self.dead[ix] = true;
+        }
+
+        true
+    }
+
+    /// The final status of the stone at `vertex`, or `None` if it's empty.
+    pub fn status(&self, vertex: Vertex) -> Option<StoneStatus> {
+        self.board.index(vertex).and_then(|ix| self.status_of(ix, &self.seki()))
+    }
+
+    /// Every stone with a particular status (`final_status_list`).
+    pub fn status_list(&self, status: StoneStatus) -> Vec<Vertex> {
+        let seki = self.seki();
+
+        (0..self.dead.len())
+            .filter(|&ix| self.status_of(ix, &seki) == Some(status))
+            .map(|ix| self.board.vertex(ix))
+            .collect()
+    }
+
+    /// The empty points (and points with dead stones on them) which are
+    /// completely surrounded by `color`'s living stones.
+    pub fn territory(&self, color: Color) -> Vec<Vertex> {
+        let mut territory = self.territory_points(color);
+        territory.sort_unstable();
+        territory.into_iter().map(|ix| self.board.vertex(ix)).collect()
+    }
+
+    /// How many points does `color` have, not counting komi?
+    pub fn points(&self, color: Color, rules: Rules) -> f32 {
+        let territory = self.territory_points(color);
+
+        match rules {
+            Rules::Area => {
+                let stones = (0..self.dead.len())
+                    .filter(|&ix| self.alive(ix) == Some(color))
+                    .count();
+
+                (stones + territory.len()) as f32
+            }
+            Rules::Territory => {
+                let dead_stones = (0..self.dead.len())
+                    .filter(|&ix| self.dead[ix] && self.point(ix) == Some(color.opponent()))
+                    .count();
+
+                (territory.len() + dead_stones) as f32 + self.board.captures(color) as f32
+            }
+        }
+    }
+
+    /// Work out who won, with `komi` added to white's score.
+    pub fn score(&self, komi: f32, rules: Rules) -> GameResult {
+        let margin = self.points(Color::Black, rules) - self.points(Color::White, rules) - komi;
+
+        if margin > 0.0 {
+            GameResult::Win(Color::Black, margin)
+        } else if margin < 0.0 {
+            GameResult::Win(Color::White, -margin)
+        } else {
+            GameResult::Draw
+        }
+    }
+
+    fn point(&self, ix: usize) -> Option<Color> {
+        self.board.get(self.board.vertex(ix))
+    }
+
+    /// The colour of the living stone on a point, if there is one.
+    fn alive(&self, ix: usize) -> Option<Color> {
+        if self.dead[ix] { None } else { self.point(ix) }
+    }
+
+    fn status_of(&self, ix: usize, seki: &[bool]) -> Option<StoneStatus> {
+        self.point(ix)?;
+
+        if self.dead[ix] {
+            Some(StoneStatus::Dead)
+        } else if seki[ix] {
+            Some(StoneStatus::Seki)
+        } else {
+            Some(StoneStatus::Alive)
+        }
+    }
+
+    /// Every point connected to `start` through points matching `predicate`.
+    fn chain<F>(&self, start: usize, predicate: F) -> Vec<usize>
+        where F: Fn(Option<Color>) -> bool
+    {
+        let mut points = vec![start];
+        let mut next = 0;
+
+        while next < points.len() {
+            for neighbour in self.board.neighbours(points[next]) {
+                if !points.contains(&neighbour) && predicate(self.point(neighbour)) {
+                    points.push(neighbour);
+                }
+            }
+
+            next += 1;
+        }
+
+        points
+    }
+
+    /// Split the points without living stones into regions, along with the
+    /// colours of the living stones around each one.
+    fn regions(&self) -> Vec<(Vec<usize>, Vec<Color>)> {
+        let mut seen = vec![false; self.dead.len()];
+        let mut regions = Vec::new();
+
+        for start in 0..self.dead.len() {
+            if seen[start] || self.alive(start).is_some() {
+                continue;
+            }
+
+            let mut points = vec![start];
+            let mut borders = Vec::new();
+            let mut next = 0;
+            seen[start] = true;
+
+            while next < points.len() {
+                for neighbour in self.board.neighbours(points[next]) {
+                    match self.alive(neighbour) {
+                        Some(color) if !borders.contains(&color) => borders.push(color),
+                        Some(_) => {}
+                        None if !seen[neighbour] => {
+                            seen[neighbour] = true;
+                            points.push(neighbour);
+                        }
+                        None => {}
+                    }
+                }
+
+                next += 1;
+            }
+
+            regions.push((points, borders));
+        }
+
+        regions
+    }
+
+    fn territory_points(&self, color: Color) -> Vec<usize> {
+        self.regions()
+            .into_iter()
+            .filter(|(_, borders)| borders.as_slice() == [color])
+            .flat_map(|(points, _)| points)
+            .collect()
+    }
+
+    /// Which living stones are in seki?
+    ///
+    /// Only the simplest kind is spotted, where groups without any eyes share
+    /// one or two empty points. Bigger shared regions are assumed to be dame
+    /// which haven't been filled in yet.
+    fn seki(&self) -> Vec<bool> {
+        let regions = self.regions();
+        let mut seki = vec![false; self.dead.len()];
+
+        for (points, borders) in &regions {
+            if borders.len() < 2 || points.len() > 2 {
+                continue;
+            }
+
+            let groups = self.groups_around(points);
+
+            if groups.iter().any(|group| self.has_eye(group, &regions)) {
+                continue;
+            }
+
+            for ix in groups.into_iter().flatten() {
+                seki[ix] = true;
+            }
+        }
+
+        seki
+    }
+
+    /// The groups of living stones next to any of `points`.
+    fn groups_around(&self, points: &[usize]) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+
+        for &ix in points {
+            for neighbour in self.board.neighbours(ix) {
+                if let Some(color) = self.alive(neighbour) {
+                    if !groups.iter().any(|group| group.contains(&neighbour)) {
+                        groups.push(self.chain(neighbour, |p| p == Some(color)));
+                    }
+                }
+            }
+        }
+
+        groups
+    }
+
+    /// Is `group` next to a region surrounded only by its own colour?
+    fn has_eye(&self, group: &[usize], regions: &[(Vec<usize>, Vec<Color>)]) -> bool {
+        let color = self.alive(group[0]);
+
+        regions.iter()
+            .filter(|(_, borders)| borders.len() == 1 && Some(borders[0]) == color)
+            .flat_map(|(points, _)| points)
+            .any(|&ix| self.board.neighbours(ix).iter().any(|n| group.contains(n)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::values::Move;
+
+    fn board(size: u32, black: &[&str], white: &[&str]) -> Board {
+        let mut board = Board::new(size).unwrap();
+        let vertices = |names: &[&str]| -> Vec<Vertex> {
+            names.iter().map(|name| name.parse().unwrap()).collect()
+        };
+
+        board.add_stones(Color::Black, &vertices(black)).unwrap();
+        board.add_stones(Color::White, &vertices(white)).unwrap();
+        board
+    }
+
+    fn vertices(list: Vec<Vertex>) -> String {
+        list.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn parse_and_print_results() {
+        let inputs = vec![("B+3.5", GameResult::Win(Color::Black, 3.5)),
+                          ("W+31", GameResult::Win(Color::White, 31.0)),
+                          ("W+R", GameResult::Resignation(Color::White)),
+                          ("B+R", GameResult::Resignation(Color::Black)),
+                          ("0", GameResult::Draw)];
+
+        for (src, should_be) in inputs {
+            let got: GameResult = src.parse().unwrap();
+            assert_eq!(got, should_be);
+            assert_eq!(got.to_string(), src);
+        }
+
+        assert_eq!("w+resign".parse::<GameResult>().unwrap(), GameResult::Resignation(Color::White));
+
+        for src in &["", "B", "B+", "B+0", "B+-2", "X+3", "W+lots", "draw"] {
+            assert!(src.parse::<GameResult>().is_err(), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn area_and_territory_scoring() {
+        // a wall down the middle, with a white prisoner taken by black and a
+        // dead black stone on white's side
+        let mut board = board(5,
+                              &["C1", "C2", "C3", "C4", "C5", "E3"],
+                              &["D1", "D2", "D3", "D4", "D5", "A2"]);
+        board.play(Move::new(Color::Black, "A1".parse().unwrap())).unwrap();
+        board.play(Move::new(Color::Black, "A3".parse().unwrap())).unwrap();
+        board.play(Move::new(Color::Black, "B2".parse().unwrap())).unwrap();
+        assert_eq!(board.captures(Color::Black), 1);
+
+        let mut scorer = Scorer::new(&board);
+        assert!(scorer.mark_dead("E3".parse().unwrap()));
+        assert!(!scorer.mark_dead("E4".parse().unwrap()));
+
+        // black: 8 stones + 7 territory, white: 5 stones + 5 territory
+        assert_eq!(scorer.points(Color::Black, Rules::Area), 15.0);
+        assert_eq!(scorer.points(Color::White, Rules::Area), 10.0);
+        assert_eq!(scorer.score(2.5, Rules::Area), GameResult::Win(Color::Black, 2.5));
+
+        // black: 7 territory + 1 prisoner, white: 5 territory + 1 dead stone
+        assert_eq!(scorer.points(Color::Black, Rules::Territory), 8.0);
+        assert_eq!(scorer.points(Color::White, Rules::Territory), 6.0);
+        assert_eq!(scorer.score(2.0, Rules::Territory), GameResult::Draw);
+
+        assert_eq!(vertices(scorer.territory(Color::White)), "E1 E2 E3 E4 E5");
+    }
+
+    #[test]
+    fn status_lists() {
+        // A1 is shared by black's A2 and B2 and white's B1, and neither
+        // group has an eye, so they're counted as being in seki
+        let board = board(5, &["A2", "B2", "E4", "D5"], &["B1", "C2", "A3", "B3", "C3", "E2"]);
+        let mut scorer = Scorer::new(&board);
+        scorer.mark_dead("E4".parse().unwrap());
+
+        assert_eq!(vertices(scorer.status_list(StoneStatus::Dead)), "E4");
+        assert_eq!(scorer.status("E2".parse().unwrap()), Some(StoneStatus::Alive));
+        assert_eq!(scorer.status("D4".parse().unwrap()), None);
+        assert_eq!(vertices(scorer.status_list(StoneStatus::Alive)), "C2 E2 A3 B3 C3 D5");
+        assert_eq!(vertices(scorer.status_list(StoneStatus::Seki)), "B1 A2 B2");
+    }
+
+    #[test]
+    fn groups_with_an_eye_are_not_in_seki() {
+        let board = board(3, &["A2", "B2", "B3"], &["A1", "B1", "C1", "C2"]);
+        let scorer = Scorer::new(&board);
+
+        // black shares C3 with white, but has an eye on A3
+        assert!(scorer.status_list(StoneStatus::Seki).is_empty());
+        assert_eq!(vertices(scorer.territory(Color::Black)), "A3");
+        assert_eq!(scorer.points(Color::Black, Rules::Area), 4.0);
+        assert_eq!(scorer.points(Color::Black, Rules::Territory), 1.0);
+    }
+
+    #[test]
+    fn unfilled_dame_between_living_groups() {
+        // C5 and D5 are still empty, but both sides have plenty of eyes
+        let dame = board(5, &["C1", "C2", "C3", "C4", "B5"], &["D1", "D2", "D3", "D4", "E5"]);
+        let scorer = Scorer::new(&dame);
+
+        assert!(scorer.status_list(StoneStatus::Seki).is_empty());
+        assert_eq!(scorer.status("B5".parse().unwrap()), Some(StoneStatus::Alive));
+        assert_eq!(scorer.points(Color::Black, Rules::Territory), 9.0);
+        assert_eq!(scorer.points(Color::White, Rules::Territory), 4.0);
+
+        // and a wide open middle isn't seki either
+        let open = board(5, &["B1", "B2", "B3", "B4", "B5"], &["D1", "D2", "D3", "D4", "D5"]);
+        let scorer = Scorer::new(&open);
+        assert!(scorer.status_list(StoneStatus::Seki).is_empty());
+    }
+}
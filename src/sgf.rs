@@ -0,0 +1,502 @@
+//! Reading and writing game records in the SGF (FF\[4\]) format.
+//!
+//! Only the parts of SGF which matter to a GTP engine are understood: the
+//! board size (`SZ`), komi (`KM`), handicap (`HA`), setup stones (`AB` and
+//! `AW`, which must be in the root node), the moves (`B` and `W`), and the
+//! result (`RE`). When a file has variations, only the main line (the first
+//! variation at each branch) is read.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use go_text_protocol::sgf::GameRecord;
+//!
+//! let record: GameRecord = "(;GM[1]FF[4]SZ[9]KM[5.5];B[ee];W[cg];B[])".parse().unwrap();
+//!
+//! assert_eq!(record.size, 9);
+//! assert_eq!(record.komi, 5.5);
+//! assert_eq!(record.moves.len(), 3);
+//! assert_eq!(record.moves[1].to_string(), "white C3");
+//! ```
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::board::MIN_BOARD_SIZE;
+use crate::engine::CommandResult;
+use crate::errors::*;
+use crate::game::{Game, DEFAULT_BOARD_SIZE};
+use crate::values::{Color, Move, Vertex, MAX_BOARD_SIZE};
+
+/// The parts of an SGF game record a GTP engine cares about.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameRecord {
+    /// The board size (`SZ`).
+    pub size: u32,
+    /// The komi (`KM`).
+    pub komi: f32,
+    /// The number of handicap stones (`HA`), which are placed using the
+    /// black setup stones.
+    pub handicap: u32,
+    /// Black stones on the board before the first move (`AB`).
+    pub black_setup: Vec<Vertex>,
+    /// White stones on the board before the first move (`AW`).
+    pub white_setup: Vec<Vertex>,
+    /// The moves played, in order.
+    pub moves: Vec<Move>,
+    /// The result (`RE`), e.g. `B+3.5` or `W+R`.
+    pub result: Option<String>,
+}
+
+impl Default for GameRecord {
+    fn default() -> GameRecord {
+        GameRecord {
+            size: DEFAULT_BOARD_SIZE,
+            komi: 0.0,
+            handicap: 0,
+            black_setup: Vec::new(),
+            white_setup: Vec::new(),
+            moves: Vec::new(),
+            result: None,
+        }
+    }
+}
+
+impl GameRecord {
+    /// Set up a `Game` from this record, playing every move before
+    /// `move_number` (counting from 1), or all of them if `move_number` is
+    /// `None`.
+    ///
+    /// Fails with the usual GTP failure message if the board size, setup
+    /// stones, or moves are invalid.
+    pub fn to_game(&self, move_number: Option<u32>) -> CommandResult<Game> {
+        let mut game = Game::new(self.size)?;
+        game.set_komi(self.komi);
+
+        game.setup(Color::Black, &self.black_setup)?;
+        game.setup(Color::White, &self.white_setup)?;
+
+        // HA means black's setup stones are handicap stones (so white moves
+        // first). They aren't placed with set_free_handicap() because plenty
+        // of files have fewer than the 2 stones it insists on.
+        if self.handicap > 0 && self.white_setup.is_empty() {
+            game.set_handicap(&self.black_setup);
+        }
+
+        let count = match move_number {
+            Some(n) => (n.saturating_sub(1) as usize).min(self.moves.len()),
+            None => self.moves.len(),
+        };
+
+        for &mv in &self.moves[..count] {
+            game.play(mv)?;
+        }
+
+        Ok(game)
+    }
+}
+
+impl<'a> From<&'a Game> for GameRecord {
+    fn from(game: &'a Game) -> GameRecord {
+        let start = game.board_at(0).expect("There's always a starting position");
+
+        GameRecord {
+            size: game.size(),
+            komi: game.komi(),
+            handicap: game.handicap().len() as u32,
+            black_setup: start.stones(Color::Black),
+            white_setup: start.stones(Color::White),
+            moves: game.moves().to_vec(),
+            result: None,
+        }
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<GameRecord> {
+        let nodes = SgfParser::new(s).main_line()?;
+        let mut record = GameRecord::default();
+
+        // the root node's properties need to be known before we can make
+        // sense of any coordinates
+        if let Some(size) = nodes.first().and_then(|root| property(root, "SZ")) {
+            let size: u32 = size.trim().parse().map_err(|_| malformed("invalid board size"))?;
+            if size < u32::from(MIN_BOARD_SIZE) || size > u32::from(MAX_BOARD_SIZE) {
+                return Err(malformed("invalid board size"));
+            }
+            record.size = size;
+        }
+
+        for (i, node) in nodes.iter().enumerate() {
+            for (name, values) in node {
+                let first = values.first().map(|v| v.trim()).unwrap_or("");
+
+                match name.as_str() {
+                    "KM" => record.komi = first.parse().map_err(|_| malformed("invalid komi"))?,
+                    "HA" => {
+                        record.handicap = first.parse().map_err(|_| malformed("invalid handicap"))?
+                    }
+                    "RE" => record.result = Some(first.to_string()),
+                    // setup stones part way through the game can't be
+                    // represented as moves
+                    "AB" | "AW" if i > 0 => return Err(malformed("setup stones after the root")),
+                    "AB" => record.black_setup.extend(points(values, record.size)?),
+                    "AW" => record.white_setup.extend(points(values, record.size)?),
+                    "B" | "W" => {
+                        let color = if name == "B" { Color::Black } else { Color::White };
+                        let vertex = point(first, record.size)?;
+                        record.moves.push(Move::new(color, vertex));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(record)
+    }
+}
+
+impl Display for GameRecord {
+    /// Write the record out as an SGF file, with up to 10 moves per line.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "(;GM[1]FF[4]SZ[{}]KM[{}]", self.size, self.komi)?;
+
+        if self.handicap > 0 {
+            write!(f, "HA[{}]", self.handicap)?;
+        }
+        if !self.black_setup.is_empty() {
+            write!(f, "AB")?;
+            for &vertex in &self.black_setup {
+                write!(f, "[{}]", coordinates(vertex, self.size))?;
+            }
+        }
+        if !self.white_setup.is_empty() {
+            write!(f, "AW")?;
+            for &vertex in &self.white_setup {
+                write!(f, "[{}]", coordinates(vertex, self.size))?;
+            }
+        }
+        if let Some(ref result) = self.result {
+            write!(f, "RE[{}]", escape(result))?;
+        }
+
+        for (i, mv) in self.moves.iter().enumerate() {
+            if i % 10 == 0 {
+                writeln!(f)?;
+            }
+
+            let color = match mv.color {
+                Color::Black => "B",
+                Color::White => "W",
+            };
+            write!(f, ";{}[{}]", color, coordinates(mv.vertex, self.size))?;
+        }
+
+        writeln!(f, ")")
+    }
+}
+
+/// A node's properties, in the order they were written.
+type Node = Vec<(String, Vec<String>)>;
+
+fn property<'a>(node: &'a Node, name: &str) -> Option<&'a str> {
+    node.iter()
+        .find(|(n, _)| n == name)
+        .and_then(|(_, values)| values.first())
+        .map(|v| v.as_str())
+}
+
+fn malformed(reason: &str) -> Error {
+    ErrorKind::MalformedSgf(reason.to_string()).into()
+}
+
+/// Convert an SGF point (e.g. `dd`) into a vertex. An empty value, or `tt` on
+/// boards up to 19x19, means a pass.
+fn point(value: &str, size: u32) -> Result<Vertex> {
+    if value.is_empty() || (size <= 19 && value == "tt") {
+        return Ok(Vertex::Pass);
+    }
+
+    let bytes = value.as_bytes();
+    let invalid = || malformed(&format!("invalid point \"{}\"", value));
+
+    if bytes.len() != 2 || !bytes.iter().all(u8::is_ascii_lowercase) {
+        return Err(invalid());
+    }
+
+    let column = u32::from(bytes[0] - b'a');
+    let row_from_top = u32::from(bytes[1] - b'a');
+
+    if column >= size || row_from_top >= size {
+        return Err(invalid());
+    }
+
+    Ok(Vertex::Point {
+        column: column as u8,
+        row: (size - 1 - row_from_top) as u8,
+    })
+}
+
+/// Convert a list of points, which may include compressed rectangles like
+/// `aa:cc`.
+fn points(values: &[String], size: u32) -> Result<Vec<Vertex>> {
+    let mut vertices = Vec::new();
+
+    for value in values {
+        let value = value.trim();
+
+        match value.find(':') {
+            None => vertices.push(point(value, size)?),
+            Some(ix) => {
+                let corners = (point(&value[..ix], size)?.coords(),
+                               point(&value[ix + 1..], size)?.coords());
+                let ((c1, r1), (c2, r2)) = match corners {
+                    (Some(a), Some(b)) => (a, b),
+                    _ => return Err(malformed("invalid rectangle")),
+                };
+
+                for row in r1.min(r2)..=r1.max(r2) {
+                    for column in c1.min(c2)..=c1.max(c2) {
+                        vertices.push(Vertex::Point { column, row });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(vertices)
+}
+
+fn coordinates(vertex: Vertex, size: u32) -> String {
+    match vertex.coords() {
+        Some((column, row)) => {
+            let row_from_top = size - 1 - u32::from(row);
+            format!("{}{}", (b'a' + column) as char, (b'a' + row_from_top as u8) as char)
+        }
+        None => String::new(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+/// A very forgiving SGF parser which only keeps the main line.
+struct SgfParser<'a> {
+    src: &'a str,
+    pointer: usize,
+}
+
+impl<'a> SgfParser<'a> {
+    fn new(src: &'a str) -> SgfParser<'a> {
+        SgfParser { src, pointer: 0 }
+    }
+
+    /// Read the nodes along the main line of the first game in the file.
+    fn main_line(&mut self) -> Result<Vec<Node>> {
+        let mut nodes = Vec::new();
+
+        self.skip_whitespace();
+        self.expect('(')?;
+        self.game_tree(Some(&mut nodes))?;
+
+        if nodes.is_empty() {
+            return Err(malformed("the game has no nodes"));
+        }
+
+        Ok(nodes)
+    }
+
+    /// Read a game tree (after its opening parenthesis), adding its nodes
+    /// to `nodes` (if provided) and following the first variation.
+    fn game_tree(&mut self, mut nodes: Option<&mut Vec<Node>>) -> Result<()> {
+        let mut seen_variation = false;
+
+        loop {
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(';') if !seen_variation => {
+                    self.pointer += 1;
+                    let node = self.node()?;
+                    if let Some(ref mut nodes) = nodes {
+                        nodes.push(node);
+                    }
+                }
+                Some('(') => {
+                    self.pointer += 1;
+                    // only the first variation is part of the main line
+                    let variation = if seen_variation { None } else { nodes.as_deref_mut() };
+                    self.game_tree(variation)?;
+                    seen_variation = true;
+                }
+                Some(')') => {
+                    self.pointer += 1;
+                    return Ok(());
+                }
+                Some(c) => return Err(malformed(&format!("unexpected {:?}", c))),
+                None => return Err(malformed("unexpected end of file")),
+            }
+        }
+    }
+
+    fn node(&mut self) -> Result<Node> {
+        let mut properties = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+
+            // FF[3] allowed lowercase letters in property names, which are
+            // ignored
+            let rest = &self.src[self.pointer..];
+            let length = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+
+            if length == 0 {
+                return Ok(properties);
+            }
+
+            let name: String = rest[..length].chars().filter(char::is_ascii_uppercase).collect();
+            self.pointer += length;
+
+            let mut values = Vec::new();
+
+            loop {
+                self.skip_whitespace();
+                if self.peek() != Some('[') {
+                    break;
+                }
+                self.pointer += 1;
+                values.push(self.value()?);
+            }
+
+            if values.is_empty() {
+                return Err(malformed(&format!("{} has no value", name)));
+            }
+
+            properties.push((name, values));
+        }
+    }
+
+    /// Read a property value (after its opening bracket), handling escapes.
+    fn value(&mut self) -> Result<String> {
+        let mut value = String::new();
+        let mut chars = self.src[self.pointer..].char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some((_, escaped)) = chars.next() {
+                        value.push(escaped);
+                    }
+                }
+                ']' => {
+                    self.pointer += i + 1;
+                    return Ok(value);
+                }
+                _ => value.push(c),
+            }
+        }
+
+        Err(malformed("unterminated property value"))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pointer..].chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pointer += c.len_utf8();
+            Ok(())
+        } else {
+            Err(malformed(&format!("expected {:?}", c)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.src[self.pointer..];
+        self.pointer += rest.len() - rest.trim_start().len();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(s: &str) -> Vertex {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn sgf_points() {
+        let inputs = vec![("aa", 19, vertex("A19")),
+                          ("ss", 19, vertex("T1")),
+                          ("dp", 19, vertex("D4")),
+                          ("ee", 9, vertex("E5")),
+                          ("", 19, Vertex::Pass),
+                          ("tt", 19, Vertex::Pass),
+                          ("tt", 21, vertex("U2")),
+                          ("ya", 25, vertex("Z25"))];
+
+        for (src, size, should_be) in inputs {
+            assert_eq!(point(src, size).unwrap(), should_be, "{:?}", src);
+            if should_be != Vertex::Pass {
+                assert_eq!(coordinates(should_be, size), src);
+            }
+        }
+
+        for &src in &["a", "zz", "A1", "jj"] {
+            assert!(point(src, 9).is_err(), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn only_the_main_line_is_read() {
+        let src = "(;SZ[9];B[ee](;W[cc];B[gg](;W[cg])(;W[gc]))(;W[gg]))";
+
+        let got: GameRecord = src.parse().unwrap();
+
+        let moves: Vec<String> = got.moves.iter().map(|mv| mv.to_string()).collect();
+        assert_eq!(moves, vec!["black E5", "white C7", "black G3", "white C3"]);
+    }
+
+    #[test]
+    fn escapes_and_compressed_points() {
+        let src = "(;FF[3]SZ[5]C[a comment with \\] in it]\n  AB[aa:bb]RE[B+\\\\R])";
+
+        let got: GameRecord = src.parse().unwrap();
+
+        assert_eq!(got.black_setup, vec![vertex("A4"), vertex("B4"), vertex("A5"), vertex("B5")]);
+        assert_eq!(got.result.as_deref(), Some("B+\\R"));
+        assert!(got.to_string().contains("RE[B+\\\\R]"));
+    }
+
+    #[test]
+    fn malformed_files() {
+        for src in &["", "(", "(;B[dd]", "(;SZ[nineteen])", "(;SZ[1])", "(;SZ[26])",
+                     "(;SZ[300];B[aa])", "(;SZ[9];B[zz])", "(;SZ[9];B[ee]AW[cc])",
+                     "(;SZ[9];B[ee];AB[cc])", "(;KM)", "x"] {
+            match src.parse::<GameRecord>() {
+                Err(ref e) if matches!(*e.kind(), ErrorKind::MalformedSgf(_)) => {}
+                other => panic!("{:?} gave {:?}", src, other),
+            }
+        }
+    }
+
+    #[test]
+    fn load_part_of_a_game() {
+        let record: GameRecord = "(;SZ[9]KM[6.5];B[ee];W[cg];B[gc])".parse().unwrap();
+
+        let game = record.to_game(Some(3)).unwrap();
+
+        assert_eq!(game.moves().len(), 2);
+        assert_eq!(game.to_play(), Color::Black);
+        assert_eq!(game.komi(), 6.5);
+
+        assert_eq!(record.to_game(Some(1)).unwrap().moves().len(), 0);
+        assert_eq!(record.to_game(Some(100)).unwrap().moves().len(), 3);
+    }
+}
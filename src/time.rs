@@ -0,0 +1,414 @@
+//! Time controls and keeping track of each player's clock.
+//!
+//! GTP only has `time_settings`, which describes main time followed by
+//! Canadian overtime, but the KGS extension `kgs-time_settings` can also
+//! describe absolute time and Japanese byo-yomi. Both are parsed into a
+//! `TimeControl`, and `Clocks` uses the `time_left` updates sent during the
+//! game to work out how long an engine can think for.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::convert::TryFrom;
+//! use std::time::Duration;
+//! use go_text_protocol::{Color, RawCommand, parse};
+//! use go_text_protocol::time::{Clocks, TimeControl};
+//!
+//! let cmd: RawCommand = parse("kgs-time_settings byoyomi 600 30 5").unwrap();
+//! let control = TimeControl::try_from(&cmd).unwrap();
+//! assert_eq!(control,
+//!            TimeControl::ByoYomi { main_time: 600, period_time: 30, periods: 5 });
+//!
+//! // out of main time, with 3 periods left
+//! let mut clocks = Clocks::new(control);
+//! clocks.time_left(Color::Black, 30, 3);
+//!
+//! let think_time = clocks.think_time(Color::Black).unwrap();
+//! assert!(think_time < Duration::from_secs(30));
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use crate::commands::StandardCommand;
+use crate::errors::*;
+use crate::parser::RawCommand;
+use crate::values::{Color, Int};
+
+/// Roughly how many more moves main time needs to last for.
+pub const MAIN_TIME_MOVES: u32 = 30;
+
+/// How much earlier than the deadline `Clocks::think_time()` aims to
+/// finish, to allow for network lag.
+pub const SAFETY_MARGIN: Duration = Duration::from_secs(1);
+
+/// A time control, with all times in seconds.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub enum TimeControl {
+    /// No time limits.
+    #[default]
+    Unlimited,
+    /// The whole game has to be played in `main_time`.
+    Absolute {
+        /// The time each player has for the whole game.
+        main_time: u32,
+    },
+    /// Japanese byo-yomi, where `periods` periods of `period_time` follow
+    /// the main time. A period is only used up if a move takes longer than
+    /// `period_time`.
+    ByoYomi {
+        /// The time before overtime starts.
+        main_time: u32,
+        /// The length of each period.
+        period_time: u32,
+        /// The number of periods.
+        periods: u32,
+    },
+    /// Canadian overtime, where `stones` moves have to be played in
+    /// `period_time` once the main time runs out.
+    Canadian {
+        /// The time before overtime starts.
+        main_time: u32,
+        /// The time allowed for each batch of `stones` moves.
+        period_time: u32,
+        /// The number of moves to play in each period.
+        stones: u32,
+    },
+}
+
+impl TimeControl {
+    /// Interpret the arguments to `time_settings` as described in section
+    /// 4.2 of the spec: a byo-yomi time of `0` means absolute time, and a
+    /// byo-yomi time without any stones means no time limits.
+    pub fn from_time_settings(main_time: u32, byo_yomi_time: u32, byo_yomi_stones: u32)
+                              -> TimeControl {
+        match (byo_yomi_time, byo_yomi_stones) {
+            (0, _) => TimeControl::Absolute { main_time },
+            (_, 0) => TimeControl::Unlimited,
+            (period_time, stones) => {
+                TimeControl::Canadian {
+                    main_time,
+                    period_time,
+                    stones,
+                }
+            }
+        }
+    }
+
+    /// The closest `time_settings` command, for engines which don't
+    /// understand `kgs-time_settings`. Byo-yomi is sent as Canadian
+    /// overtime with one stone per period.
+    pub fn to_time_settings(&self) -> StandardCommand {
+        let (main_time, byo_yomi_time, byo_yomi_stones) = match *self {
+            TimeControl::Unlimited => (0, 1, 0),
+            TimeControl::Absolute { main_time } => (main_time, 0, 0),
+            TimeControl::ByoYomi { main_time, period_time, .. } => (main_time, period_time, 1),
+            TimeControl::Canadian { main_time, period_time, stones } => {
+                (main_time, period_time, stones)
+            }
+        };
+
+        StandardCommand::TimeSettings {
+            main_time,
+            byo_yomi_time,
+            byo_yomi_stones,
+        }
+    }
+
+    /// The time each player starts with.
+    pub fn main_time(&self) -> u32 {
+        match *self {
+            TimeControl::Unlimited => 0,
+            TimeControl::Absolute { main_time } |
+            TimeControl::ByoYomi { main_time, .. } |
+            TimeControl::Canadian { main_time, .. } => main_time,
+        }
+    }
+
+    /// How a player's clock looks when overtime starts.
+    fn overtime(&self) -> Clock {
+        match *self {
+            TimeControl::Unlimited | TimeControl::Absolute { .. } => Clock::new(0, 0),
+            TimeControl::ByoYomi { period_time, periods, .. } => Clock::new(period_time, periods),
+            TimeControl::Canadian { period_time, stones, .. } => Clock::new(period_time, stones),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a RawCommand> for TimeControl {
+    type Error = Error;
+
+    /// Parse either a `time_settings` or a `kgs-time_settings` command.
+    fn try_from(raw: &'a RawCommand) -> Result<TimeControl> {
+        let int = |index| raw.arg::<Int>(index).map(u32::from);
+
+        match raw.name.to_lowercase().as_str() {
+            "time_settings" => {
+                raw.check_arg_count(3, 3)?;
+                Ok(TimeControl::from_time_settings(int(0)?, int(1)?, int(2)?))
+            }
+            "kgs-time_settings" => {
+                raw.check_arg_count(1, 4)?;
+                let kind = raw.args[0].to_lowercase();
+
+                let expected = match kind.as_str() {
+                    "none" => 1,
+                    "absolute" => 2,
+                    "byoyomi" | "canadian" => 4,
+                    _ => bail!(ErrorKind::InvalidArgument(0, raw.args[0].clone())),
+                };
+                raw.check_arg_count(expected, expected)?;
+
+                let control = match kind.as_str() {
+                    "none" => TimeControl::Unlimited,
+                    "absolute" => TimeControl::Absolute { main_time: int(1)? },
+                    "byoyomi" => {
+                        TimeControl::ByoYomi {
+                            main_time: int(1)?,
+                            period_time: int(2)?,
+                            periods: int(3)?,
+                        }
+                    }
+                    _ => {
+                        TimeControl::Canadian {
+                            main_time: int(1)?,
+                            period_time: int(2)?,
+                            stones: int(3)?,
+                        }
+                    }
+                };
+
+                Ok(control)
+            }
+            _ => bail!(ErrorKind::UnknownCommand(raw.name.clone())),
+        }
+    }
+}
+
+impl Display for TimeControl {
+    /// Write the time control as the arguments to `kgs-time_settings`.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            TimeControl::Unlimited => write!(f, "none"),
+            TimeControl::Absolute { main_time } => write!(f, "absolute {}", main_time),
+            TimeControl::ByoYomi { main_time, period_time, periods } => {
+                write!(f, "byoyomi {} {} {}", main_time, period_time, periods)
+            }
+            TimeControl::Canadian { main_time, period_time, stones } => {
+                write!(f, "canadian {} {} {}", main_time, period_time, stones)
+            }
+        }
+    }
+}
+
+/// A player's clock, in the same terms as `time_left`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Clock {
+    /// The seconds left in main time, or in the current overtime period.
+    pub time: u32,
+    /// `0` while in main time, otherwise the stones left to play in this
+    /// period (Canadian) or the periods left (byo-yomi).
+    pub stones: u32,
+}
+
+impl Clock {
+    /// Create a new `Clock`.
+    pub fn new(time: u32, stones: u32) -> Clock {
+        Clock { time, stones }
+    }
+
+    /// Has the player run out of main time?
+    pub fn in_overtime(&self) -> bool {
+        self.stones > 0
+    }
+}
+
+/// Both players' clocks under a particular time control.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Clocks {
+    control: TimeControl,
+    black: Clock,
+    white: Clock,
+}
+
+impl Clocks {
+    /// Start both clocks at the beginning of the game.
+    pub fn new(control: TimeControl) -> Clocks {
+        let clock = if control.main_time() > 0 {
+            Clock::new(control.main_time(), 0)
+        } else {
+            control.overtime()
+        };
+
+        Clocks {
+            control,
+            black: clock,
+            white: clock,
+        }
+    }
+
+    /// The time control being used.
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    /// A player's clock.
+    pub fn clock(&self, color: Color) -> Clock {
+        match color {
+            Color::Black => self.black,
+            Color::White => self.white,
+        }
+    }
+
+    /// Update a player's clock from a `time_left` command.
+    pub fn time_left(&mut self, color: Color, time: u32, stones: u32) {
+        let clock = Clock::new(time, stones);
+
+        match color {
+            Color::Black => self.black = clock,
+            Color::White => self.white = clock,
+        }
+    }
+
+    /// How long `color` can afford to spend on their next move, or `None`
+    /// if there are no time limits.
+    ///
+    /// Main time is spread over the next `MAIN_TIME_MOVES` moves, although
+    /// never less than overtime would allow. In overtime, byo-yomi allows
+    /// the whole period and Canadian overtime shares the time left between
+    /// the stones left to play. Either way, `SAFETY_MARGIN` is kept in
+    /// reserve.
+    pub fn think_time(&self, color: Color) -> Option<Duration> {
+        let clock = self.clock(color);
+
+        let per_move_overtime = match self.control {
+            TimeControl::Unlimited => return None,
+            TimeControl::Absolute { .. } => 0,
+            TimeControl::ByoYomi { period_time, .. } => period_time,
+            TimeControl::Canadian { period_time, stones, .. } => period_time / stones.max(1),
+        };
+
+        let think_time = if !clock.in_overtime() {
+            let share = Duration::from_secs(u64::from(clock.time)) / MAIN_TIME_MOVES;
+            share.max(Duration::from_secs(u64::from(per_move_overtime)))
+        } else {
+            // the clock is all we've got left, so don't assume a full period
+            match self.control {
+                TimeControl::Canadian { .. } => {
+                    Duration::from_secs(u64::from(clock.time)) / clock.stones
+                }
+                _ => Duration::from_secs(u64::from(clock.time)),
+            }
+        };
+
+        Some(think_time.saturating_sub(SAFETY_MARGIN))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn control(line: &str) -> Result<TimeControl> {
+        let raw: RawCommand = parse(line).unwrap();
+        TimeControl::try_from(&raw)
+    }
+
+    #[test]
+    fn parse_time_settings() {
+        let inputs = vec![("time_settings 300 0 0", TimeControl::Absolute { main_time: 300 }),
+                          ("time_settings 0 1 0", TimeControl::Unlimited),
+                          ("time_settings 600 300 25",
+                           TimeControl::Canadian {
+                               main_time: 600,
+                               period_time: 300,
+                               stones: 25,
+                           }),
+                          ("kgs-time_settings none", TimeControl::Unlimited),
+                          ("kgs-time_settings absolute 1800",
+                           TimeControl::Absolute { main_time: 1800 }),
+                          ("kgs-time_settings byoyomi 600 30 5",
+                           TimeControl::ByoYomi {
+                               main_time: 600,
+                               period_time: 30,
+                               periods: 5,
+                           }),
+                          ("kgs-time_settings canadian 0 300 25",
+                           TimeControl::Canadian {
+                               main_time: 0,
+                               period_time: 300,
+                               stones: 25,
+                           })];
+
+        for (src, should_be) in inputs {
+            assert_eq!(control(src).unwrap(), should_be, "{}", src);
+        }
+    }
+
+    #[test]
+    fn invalid_time_settings() {
+        let inputs = vec!["time_settings 300 30",
+                          "time_settings 300 -30 5",
+                          "kgs-time_settings",
+                          "kgs-time_settings none 5",
+                          "kgs-time_settings absolute",
+                          "kgs-time_settings byoyomi 600 30",
+                          "kgs-time_settings hourglass 600",
+                          "time_left black 30 0"];
+
+        for src in inputs {
+            assert!(control(src).is_err(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn time_controls_round_trip() {
+        for src in &["none", "absolute 1800", "byoyomi 600 30 5", "canadian 0 300 25"] {
+            let got = control(&format!("kgs-time_settings {}", src)).unwrap();
+            assert_eq!(got.to_string(), *src);
+        }
+
+        let byo_yomi = control("kgs-time_settings byoyomi 600 30 5").unwrap();
+        assert_eq!(byo_yomi.to_time_settings().to_string(), "time_settings 600 30 1");
+        assert_eq!(TimeControl::Unlimited.to_time_settings().to_string(), "time_settings 0 1 0");
+    }
+
+    #[test]
+    fn clocks_start_in_overtime_without_main_time() {
+        let clocks = Clocks::new(control("time_settings 0 300 25").unwrap());
+        assert_eq!(clocks.clock(Color::White), Clock::new(300, 25));
+
+        let clocks = Clocks::new(control("kgs-time_settings byoyomi 600 30 5").unwrap());
+        assert_eq!(clocks.clock(Color::Black), Clock::new(600, 0));
+    }
+
+    #[test]
+    fn how_long_to_think() {
+        let seconds = |n| Some(Duration::from_secs(n));
+
+        let mut clocks = Clocks::new(control("time_settings 600 300 25").unwrap());
+        assert_eq!(clocks.think_time(Color::Black), seconds(19));
+
+        // in overtime with 10 stones left to play in 100 seconds
+        clocks.time_left(Color::Black, 100, 10);
+        assert_eq!(clocks.think_time(Color::Black), seconds(9));
+        assert_eq!(clocks.think_time(Color::White), seconds(19));
+
+        let mut clocks = Clocks::new(control("kgs-time_settings byoyomi 60 30 5").unwrap());
+        assert_eq!(clocks.think_time(Color::Black), seconds(29));
+        clocks.time_left(Color::Black, 30, 1);
+        assert_eq!(clocks.think_time(Color::Black), seconds(29));
+        clocks.time_left(Color::Black, 5, 1);
+        assert_eq!(clocks.think_time(Color::Black), seconds(4));
+
+        let mut clocks = Clocks::new(control("kgs-time_settings absolute 300").unwrap());
+        assert_eq!(clocks.think_time(Color::Black), seconds(9));
+        clocks.time_left(Color::Black, 0, 0);
+        assert_eq!(clocks.think_time(Color::Black), seconds(0));
+
+        assert_eq!(Clocks::default().think_time(Color::White), None);
+    }
+}
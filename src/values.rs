@@ -0,0 +1,457 @@
+//! The value types used by the `Go Text Protocol`.
+//!
+//! Section 2.9 of the GTP v2 specification defines a handful of "simple
+//! entities" which are used as command arguments and in responses. Each of
+//! them implements `FromStr` and `Display`, so they can be parsed from (and
+//! written back out as) their wire format.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use go_text_protocol::{Color, Move, Vertex};
+//!
+//! let mv: Move = "w d5".parse().unwrap();
+//!
+//! assert_eq!(mv.color, Color::White);
+//! assert_eq!(mv.vertex, Vertex::Point { column: 3, row: 4 });
+//! assert_eq!(mv.to_string(), "white D5");
+//! ```
+//!
+//! You'll usually get these by asking a `RawCommand` for one of its
+//! arguments.
+//!
+//! ```rust
+//! use go_text_protocol::{RawCommand, Color, Vertex, parse};
+//!
+//! let cmd: RawCommand = parse("play black pass").unwrap();
+//!
+//! assert_eq!(cmd.arg::<Color>(0).unwrap(), Color::Black);
+//! assert_eq!(cmd.arg::<Vertex>(1).unwrap(), Vertex::Pass);
+//! ```
+
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::errors::*;
+
+/// The largest board size the protocol can describe.
+pub const MAX_BOARD_SIZE: u8 = 25;
+
+/// The letters used for each column, note that `I` is skipped.
+pub(crate) const COLUMN_LETTERS: &[u8] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
+fn invalid(type_name: &'static str, value: &str) -> Error {
+    ErrorKind::InvalidValue(type_name, value.to_string()).into()
+}
+
+/// A non-negative integer in the range `0 <= x <= 2^31 - 1`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct Int(pub u32);
+
+impl Int {
+    /// The largest value an `Int` is allowed to hold.
+    pub const MAX: u32 = (1 << 31) - 1;
+}
+
+impl FromStr for Int {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Int> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid("int", s));
+        }
+
+        match u32::from_str(s) {
+            Ok(n) if n <= Int::MAX => Ok(Int(n)),
+            _ => Err(invalid("int", s)),
+        }
+    }
+}
+
+impl Display for Int {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<Int> for u32 {
+    fn from(other: Int) -> u32 {
+        other.0
+    }
+}
+
+/// A floating point number representable by a 32 bit IEEE 754 float.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct Float(pub f32);
+
+impl FromStr for Float {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Float> {
+        match f32::from_str(s) {
+            Ok(n) if n.is_finite() => Ok(Float(n)),
+            _ => Err(invalid("float", s)),
+        }
+    }
+}
+
+impl Hash for Float {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // 0.0 and -0.0 are equal so they need to hash the same, and NaN
+        // can't be parsed so it doesn't need special treatment
+        let normalised = if self.0 == 0.0 { 0.0 } else { self.0 };
+        normalised.to_bits().hash(state);
+    }
+}
+
+impl Display for Float {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<Float> for f32 {
+    fn from(other: Float) -> f32 {
+        other.0
+    }
+}
+
+/// Either the string `true` or the string `false`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Boolean(pub bool);
+
+impl FromStr for Boolean {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Boolean> {
+        match s {
+            "true" => Ok(Boolean(true)),
+            "false" => Ok(Boolean(false)),
+            _ => Err(invalid("boolean", s)),
+        }
+    }
+}
+
+impl Display for Boolean {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<Boolean> for bool {
+    fn from(other: Boolean) -> bool {
+        other.0
+    }
+}
+
+/// The colour of a player or stone.
+///
+/// When parsing, `b`, `black`, `w`, and `white` are accepted (case
+/// insensitive). It is always written out as `black` or `white`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Color {
+    /// The black player.
+    Black,
+    /// The white player.
+    White,
+}
+
+impl Color {
+    /// Get the other player's colour.
+    pub fn opponent(&self) -> Color {
+        match *self {
+            Color::Black => Color::White,
+            Color::White => Color::Black,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Color> {
+        match s.to_lowercase().as_str() {
+            "b" | "black" => Ok(Color::Black),
+            "w" | "white" => Ok(Color::White),
+            _ => Err(invalid("color", s)),
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Color::Black => write!(f, "black"),
+            Color::White => write!(f, "white"),
+        }
+    }
+}
+
+/// A point on the board, or one of the special "moves" `pass` and `resign`.
+///
+/// Points are written as a column letter (`A` to `Z`, skipping `I`) followed
+/// by a row number, with `A1` being the lower left corner of the board. Both
+/// `column` and `row` are zero-based, so `A1` is `Point { column: 0, row: 0 }`.
+///
+/// ```rust
+/// use go_text_protocol::Vertex;
+///
+/// let j10: Vertex = "j10".parse().unwrap();
+/// assert_eq!(j10, Vertex::Point { column: 8, row: 9 });
+/// assert_eq!(j10.to_string(), "J10");
+///
+/// assert!("I5".parse::<Vertex>().is_err());
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Vertex {
+    /// A point on the board.
+    Point {
+        /// The zero-based column, counting from the left.
+        column: u8,
+        /// The zero-based row, counting from the bottom.
+        row: u8,
+    },
+    /// Passing.
+    Pass,
+    /// Resigning. This is only valid as the result of `genmove`.
+    Resign,
+}
+
+impl Vertex {
+    /// Create a new point, returning `None` if it would lie outside the
+    /// largest possible board.
+    pub fn point(column: u8, row: u8) -> Option<Vertex> {
+        if column < MAX_BOARD_SIZE && row < MAX_BOARD_SIZE {
+            Some(Vertex::Point { column, row })
+        } else {
+            None
+        }
+    }
+
+    /// Get the `(column, row)` coordinates if this is a point on the board.
+    pub fn coords(&self) -> Option<(u8, u8)> {
+        match *self {
+            Vertex::Point { column, row } => Some((column, row)),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Vertex {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Vertex> {
+        let lower = s.to_lowercase();
+
+        match lower.as_str() {
+            "pass" => return Ok(Vertex::Pass),
+            "resign" => return Ok(Vertex::Resign),
+            _ => {}
+        }
+
+        let mut chars = lower.chars();
+        let letter = chars.next().map(|c| c.to_ascii_uppercase() as u32);
+        let digits = chars.as_str();
+
+        let column = letter.and_then(|l| COLUMN_LETTERS.iter().position(|&c| c as u32 == l));
+
+        let row = if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+            u8::from_str(digits).ok()
+        } else {
+            None
+        };
+
+        match (column, row) {
+            (Some(column), Some(row)) if row >= 1 => {
+                Vertex::point(column as u8, row - 1).ok_or_else(|| invalid("vertex", s))
+            }
+            _ => Err(invalid("vertex", s)),
+        }
+    }
+}
+
+impl Display for Vertex {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Vertex::Point { column, row } => {
+                write!(f, "{}{}", COLUMN_LETTERS[column as usize] as char, row + 1)
+            }
+            Vertex::Pass => write!(f, "pass"),
+            Vertex::Resign => write!(f, "resign"),
+        }
+    }
+}
+
+/// The status of a stone at the end of the game, as used by
+/// `final_status_list`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum StoneStatus {
+    /// The stone will stay on the board.
+    Alive,
+    /// The stone will be removed as a prisoner.
+    Dead,
+    /// The stone is part of a seki.
+    Seki,
+}
+
+impl FromStr for StoneStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<StoneStatus> {
+        match s {
+            "alive" => Ok(StoneStatus::Alive),
+            "dead" => Ok(StoneStatus::Dead),
+            "seki" => Ok(StoneStatus::Seki),
+            _ => Err(invalid("stone status", s)),
+        }
+    }
+}
+
+impl Display for StoneStatus {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            StoneStatus::Alive => write!(f, "alive"),
+            StoneStatus::Dead => write!(f, "dead"),
+            StoneStatus::Seki => write!(f, "seki"),
+        }
+    }
+}
+
+/// A colour and vertex, separated by whitespace (e.g. `black D5`).
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Move {
+    /// Who is making the move.
+    pub color: Color,
+    /// Where they are playing.
+    pub vertex: Vertex,
+}
+
+impl Move {
+    /// Create a new move.
+    pub fn new(color: Color, vertex: Vertex) -> Move {
+        Move { color, vertex }
+    }
+}
+
+impl FromStr for Move {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Move> {
+        let mut words = s.split_whitespace();
+
+        match (words.next(), words.next(), words.next()) {
+            (Some(color), Some(vertex), None) => {
+                let color = color.parse().chain_err(|| ErrorKind::InvalidValue("move", s.to_string()))?;
+                let vertex = vertex.parse().chain_err(|| ErrorKind::InvalidValue("move", s.to_string()))?;
+                Ok(Move::new(color, vertex))
+            }
+            _ => Err(invalid("move", s)),
+        }
+    }
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.color, self.vertex)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ints() {
+        let inputs = vec![("0", Some(0)),
+                          ("42", Some(42)),
+                          ("2147483647", Some(2147483647)),
+                          ("2147483648", None),
+                          ("+5", None),
+                          ("-1", None),
+                          ("", None),
+                          ("4.5", None)];
+
+        for (src, should_be) in inputs {
+            let got = src.parse::<Int>().ok();
+            assert_eq!(got, should_be.map(Int), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn parse_floats_and_booleans() {
+        assert_eq!("6.5".parse::<Float>().unwrap(), Float(6.5));
+        assert_eq!("-3".parse::<Float>().unwrap(), Float(-3.0));
+        assert!("inf".parse::<Float>().is_err());
+        assert!("NaN".parse::<Float>().is_err());
+        assert_eq!(Float(6.5).to_string(), "6.5");
+
+        assert_eq!("true".parse::<Boolean>().unwrap(), Boolean(true));
+        assert_eq!("false".parse::<Boolean>().unwrap(), Boolean(false));
+        assert!("1".parse::<Boolean>().is_err());
+        assert_eq!(Boolean(false).to_string(), "false");
+    }
+
+    #[test]
+    fn parse_colors() {
+        for src in &["b", "B", "black", "BLACK", "Black"] {
+            assert_eq!(src.parse::<Color>().unwrap(), Color::Black);
+        }
+        for src in &["w", "W", "white", "WhItE"] {
+            assert_eq!(src.parse::<Color>().unwrap(), Color::White);
+        }
+        assert!("blue".parse::<Color>().is_err());
+        assert_eq!(Color::White.opponent(), Color::Black);
+    }
+
+    #[test]
+    fn parse_vertices() {
+        let inputs = vec![("A1", Some(Vertex::Point { column: 0, row: 0 })),
+                          ("d5", Some(Vertex::Point { column: 3, row: 4 })),
+                          ("H8", Some(Vertex::Point { column: 7, row: 7 })),
+                          ("J9", Some(Vertex::Point { column: 8, row: 8 })),
+                          ("Z25", Some(Vertex::Point { column: 24, row: 24 })),
+                          ("PASS", Some(Vertex::Pass)),
+                          ("resign", Some(Vertex::Resign)),
+                          ("I5", None),
+                          ("A0", None),
+                          ("A26", None),
+                          ("A", None),
+                          ("5", None),
+                          ("AA1", None),
+                          ("A+1", None),
+                          ("", None)];
+
+        for (src, should_be) in inputs {
+            let got = src.parse::<Vertex>().ok();
+            assert_eq!(got, should_be, "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn vertices_round_trip() {
+        for column in 0..MAX_BOARD_SIZE {
+            for row in 0..MAX_BOARD_SIZE {
+                let vertex = Vertex::point(column, row).unwrap();
+                let got: Vertex = vertex.to_string().parse().unwrap();
+                assert_eq!(got, vertex);
+            }
+        }
+
+        assert_eq!(Vertex::Pass.to_string(), "pass");
+        assert_eq!(Vertex::Resign.to_string(), "resign");
+        assert_eq!(Vertex::point(MAX_BOARD_SIZE, 0), None);
+    }
+
+    #[test]
+    fn parse_moves() {
+        let got: Move = "B  q16".parse().unwrap();
+        assert_eq!(got, Move::new(Color::Black, Vertex::Point { column: 15, row: 15 }));
+        assert_eq!(got.to_string(), "black Q16");
+
+        assert!("black".parse::<Move>().is_err());
+        assert!("black D5 extra".parse::<Move>().is_err());
+        assert!("D5 black".parse::<Move>().is_err());
+    }
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "tokio")]
+
+extern crate go_text_protocol;
+
+mod common;
+
+use go_text_protocol::async_io::{AsyncController, AsyncProcessController, run_session_async,
+                                 run_session_async_with};
+use go_text_protocol::engine::{CommandResult, SessionOptions, run_session_with};
+use go_text_protocol::{Color, Engine, Move, Vertex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+
+fn fake_engine() -> AsyncProcessController {
+    AsyncController::spawn(&mut Command::new(common::fake_engine_path())).unwrap()
+}
+
+#[tokio::test]
+async fn talk_to_a_subprocess() {
+    let mut engine = fake_engine();
+    let a1 = Vertex::Point { column: 0, row: 0 };
+
+    assert_eq!(engine.protocol_version().await.unwrap(), 2);
+    assert_eq!(engine.name().await.unwrap(), "Fake Engine");
+    assert!(engine.known_command("undo").await.unwrap());
+
+    engine.boardsize(9).await.unwrap();
+    let reply = engine.genmove(Color::Black).await.unwrap();
+    assert_eq!(reply, Move::new(Color::Black, a1));
+    assert!(engine.play(Move::new(Color::White, a1)).await.is_err());
+
+    engine.quit().await.unwrap();
+}
+
+struct Passer;
+
+impl Engine for Passer {
+    fn name(&self) -> String {
+        "Passer".to_string()
+    }
+
+    fn version(&self) -> String {
+        "1".to_string()
+    }
+
+    fn boardsize(&mut self, _size: u32) -> CommandResult<()> {
+        Ok(())
+    }
+
+    fn clear_board(&mut self) -> CommandResult<()> {
+        Ok(())
+    }
+
+    fn komi(&mut self, _komi: f32) -> CommandResult<()> {
+        Ok(())
+    }
+
+    fn play(&mut self, _mv: Move) -> CommandResult<()> {
+        Ok(())
+    }
+
+    fn genmove(&mut self, _color: Color) -> CommandResult<Vertex> {
+        Ok(Vertex::Pass)
+    }
+}
+
+#[tokio::test]
+async fn run_an_async_session() {
+    let (mut controller, engine_side) = tokio::io::duplex(1024);
+    let (reader, writer) = tokio::io::split(engine_side);
+
+    let session = tokio::spawn(async move {
+        run_session_async(reader, writer, &mut Passer).await.unwrap();
+    });
+
+    controller.write_all(b"1 name\n# comment\n2 genmove b\n3 foo\n4 quit\n").await.unwrap();
+
+    let mut output = String::new();
+    controller.read_to_string(&mut output).await.unwrap();
+    session.await.unwrap();
+
+    assert_eq!(output, "=1 Passer\n\n=2 pass\n\n?3 unknown command\n\n=4\n\n");
+}
+
+async fn run_async(input: &'static [u8], options: SessionOptions) -> String {
+    let (mut controller, engine_side) = tokio::io::duplex(1024);
+    let (reader, writer) = tokio::io::split(engine_side);
+
+    let session = tokio::spawn(async move {
+        run_session_async_with(reader, writer, &mut Passer, options).await.unwrap();
+    });
+
+    controller.write_all(input).await.unwrap();
+    controller.shutdown().await.unwrap();
+
+    let mut output = String::new();
+    controller.read_to_string(&mut output).await.unwrap();
+    session.await.unwrap();
+
+    output
+}
+
+#[tokio::test]
+async fn async_sessions_answer_the_same_as_sync_ones() {
+    let input: &[u8] = b"7 play black\n4294967296 name\n007 name\n8\n10 known_command \xff\n\
+                         9 genmove w\n";
+
+    for &verbatim_ids in &[false, true] {
+        let options = SessionOptions { verbatim_ids };
+
+        let mut sync_output = Vec::new();
+        run_session_with(input, &mut sync_output, &mut Passer, options).unwrap();
+        let got = run_async(input, options).await;
+
+        assert_eq!(got, String::from_utf8(sync_output).unwrap());
+        assert!(got.starts_with("?7 syntax error\n\n"), "{}", got);
+        assert!(got.contains("=10 false\n\n"), "{}", got);
+    }
+
+    let got = run_async(b"007 name\n", SessionOptions { verbatim_ids: true }).await;
+    assert_eq!(got, "=007 Passer\n\n");
+}
@@ -0,0 +1,21 @@
+//! Helpers shared by the integration tests.
+
+use std::env;
+use std::path::PathBuf;
+
+/// The path to the `fake-engine` example, which `cargo test` builds along
+/// with the integration tests.
+pub fn fake_engine_path() -> PathBuf {
+    // test executables are in target/<profile>/deps/ and examples are in
+    // target/<profile>/examples/
+    let mut dir = env::current_exe().unwrap();
+    dir.pop();
+    if dir.ends_with("deps") {
+        dir.pop();
+    }
+
+    let path = dir.join("examples").join(format!("fake-engine{}", env::consts::EXE_SUFFIX));
+    assert!(path.exists(), "{} hasn't been built", path.display());
+
+    path
+}
@@ -0,0 +1,65 @@
+extern crate go_text_protocol;
+
+mod common;
+
+use std::process::Command;
+
+use go_text_protocol::{Color, ErrorKind, Move, Vertex};
+use go_text_protocol::controller::{Controller, ProcessController};
+
+fn fake_engine() -> ProcessController {
+    Controller::spawn(&mut Command::new(common::fake_engine_path())).unwrap()
+}
+
+#[test]
+fn talk_to_a_subprocess() {
+    let mut engine = fake_engine();
+
+    assert_eq!(engine.protocol_version().unwrap(), 2);
+    assert_eq!(engine.name().unwrap(), "Fake Engine");
+    assert!(engine.known_command("undo").unwrap());
+    assert!(!engine.known_command("showboard").unwrap());
+    assert!(engine.list_commands().unwrap().contains(&"genmove".to_string()));
+
+    engine.quit().unwrap();
+}
+
+#[test]
+fn play_a_short_game() {
+    let mut engine = fake_engine();
+    let a1 = Vertex::Point { column: 0, row: 0 };
+    let b1 = Vertex::Point { column: 1, row: 0 };
+
+    engine.boardsize(9).unwrap();
+    engine.komi(6.5).unwrap();
+    engine.play(Move::new(Color::Black, a1)).unwrap();
+
+    let reply = engine.genmove(Color::White).unwrap();
+    assert_eq!(reply, Move::new(Color::White, b1));
+
+    engine.undo().unwrap();
+    engine.undo().unwrap();
+
+    let reply = engine.genmove(Color::Black).unwrap();
+    assert_eq!(reply, Move::new(Color::Black, a1));
+
+    engine.quit().unwrap();
+}
+
+#[test]
+fn failures_are_reported_as_errors() {
+    let mut engine = fake_engine();
+    let e5 = Vertex::Point { column: 4, row: 4 };
+
+    engine.play(Move::new(Color::Black, e5)).unwrap();
+
+    match *engine.play(Move::new(Color::White, e5)).unwrap_err().kind() {
+        ErrorKind::ResponseFailure(ref msg) => assert_eq!(msg, "illegal move"),
+        ref other => panic!("Unexpected error: {:?}", other),
+    }
+    assert!(engine.boardsize(42).is_err());
+
+    let response = engine.send("showboard").unwrap();
+    assert!(!response.is_success());
+    assert_eq!(response.payload, "unknown command");
+}
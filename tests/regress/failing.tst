@@ -0,0 +1,6 @@
+boardsize 9
+
+1 reg_genmove black
+#? [E5]
+2 reg_genmove black
+#? [(A|B)1]*
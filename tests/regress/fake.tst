@@ -0,0 +1,17 @@
+# The fake engine always picks the first empty point, starting from A1.
+boardsize 9
+clear_board
+
+1 reg_genmove black
+#? [A1]
+
+play black A1
+
+2 reg_genmove white
+#? [B1|C1]
+
+# it never gets any better than that
+3 reg_genmove white
+#? [!B1]*
+4 reg_genmove black
+#? [PASS]*
@@ -0,0 +1,79 @@
+extern crate go_text_protocol;
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use go_text_protocol::controller::{Controller, ProcessController};
+use go_text_protocol::regression::{self, Outcome};
+
+fn fake_engine() -> ProcessController {
+    Controller::spawn(&mut Command::new(common::fake_engine_path())).unwrap()
+}
+
+fn sample(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("regress").join(name)
+}
+
+fn gtp_regress(files: &[&str]) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_gtp-regress"))
+        .args(files.iter().map(|name| sample(name)))
+        .arg("--")
+        .arg(common::fake_engine_path())
+        .output()
+        .unwrap();
+
+    (output.status.success(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn run_a_regression_file() {
+    let src = fs::read_to_string(sample("fake.tst")).unwrap();
+    let steps = regression::parse_tests(&src).unwrap();
+    let mut engine = fake_engine();
+
+    let summary = regression::run_tests(&mut engine, &steps).unwrap();
+
+    let outcomes: Vec<Outcome> = summary.results.iter().map(|r| r.outcome).collect();
+    assert_eq!(outcomes,
+               vec![Outcome::Passed,
+                    Outcome::Passed,
+                    Outcome::ExpectedFailure,
+                    Outcome::ExpectedFailure]);
+    assert_eq!(summary.results[1].answer, "B1");
+    assert!(summary.is_success());
+
+    engine.quit().unwrap();
+}
+
+#[test]
+fn the_binary_reports_surprises() {
+    let (success, stdout) = gtp_regress(&["fake.tst"]);
+    assert!(success);
+    assert!(stdout.ends_with("2 passed, 0 failed, 0 unexpected passes, 2 expected failures\n"));
+
+    let (success, stdout) = gtp_regress(&["fake.tst", "failing.tst"]);
+    assert!(!success);
+    assert!(stdout.contains("failing.tst:3: 1 FAILED: Correct '[E5]', got 'A1'\n"));
+    assert!(stdout.contains("failing.tst:5: 2 unexpected PASS!\n"));
+    assert!(stdout.ends_with("Total: 2 passed, 1 failed, 1 unexpected passes, \
+                              2 expected failures\n"));
+}
+
+#[test]
+fn relative_engine_paths_are_relative_to_the_caller() {
+    let engine = common::fake_engine_path();
+    let relative = Path::new(".").join(engine.file_name().unwrap());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_gtp-regress"))
+        .current_dir(engine.parent().unwrap())
+        .arg(sample("fake.tst"))
+        .arg("--")
+        .arg(relative)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
@@ -0,0 +1,102 @@
+extern crate go_text_protocol;
+
+use std::fs;
+use std::path::PathBuf;
+
+use go_text_protocol::game::Game;
+use go_text_protocol::sgf::GameRecord;
+use go_text_protocol::{Color, Vertex};
+
+fn sample(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("sgf").join(name)
+}
+
+fn read_sample(name: &str) -> String {
+    fs::read_to_string(sample(name)).unwrap()
+}
+
+fn vertex(s: &str) -> Vertex {
+    s.parse().unwrap()
+}
+
+#[test]
+fn round_trip_the_samples() {
+    for name in &["nine-by-nine.sgf", "handicap.sgf"] {
+        let src = read_sample(name);
+
+        let record: GameRecord = src.parse().unwrap();
+        assert_eq!(record.to_string(), src, "{}", name);
+
+        // and going through a Game loses nothing but the result
+        let game = record.to_game(None).unwrap();
+        let mut got = GameRecord::from(&game);
+        got.result = record.result.clone();
+        assert_eq!(got, record, "{}", name);
+    }
+}
+
+#[test]
+fn handicap_games_start_with_white() {
+    let record: GameRecord = read_sample("handicap.sgf").parse().unwrap();
+
+    assert_eq!(record.handicap, 2);
+    assert_eq!(record.black_setup, vec![vertex("D4"), vertex("Q16")]);
+    assert_eq!(record.result.as_deref(), Some("B+R"));
+
+    let game = record.to_game(Some(1)).unwrap();
+    assert_eq!(game.handicap(), &[vertex("D4"), vertex("Q16")][..]);
+    assert_eq!(game.to_play(), Color::White);
+
+    // black's fourth move captured the stone on T19
+    let game = record.to_game(None).unwrap();
+    assert_eq!(game.board().get(vertex("T19")), None);
+    assert_eq!(game.board().captures(Color::Black), 1);
+}
+
+#[test]
+fn handicaps_with_fewer_than_two_stones() {
+    let record: GameRecord = "(;GM[1]FF[4]SZ[9]HA[2]AB[ee];W[cc])".parse().unwrap();
+
+    let game = record.to_game(Some(1)).unwrap();
+    assert_eq!(game.board().get(vertex("E5")), Some(Color::Black));
+    assert_eq!(game.handicap(), &[vertex("E5")][..]);
+    assert_eq!(game.to_play(), Color::White);
+
+    // and the handicap stones might not be setup stones at all
+    let record: GameRecord = "(;GM[1]FF[4]SZ[9]HA[2];B[ee];B[cc];W[gg])".parse().unwrap();
+    let game = record.to_game(None).unwrap();
+    assert_eq!(game.board().stones(Color::Black).len(), 2);
+    assert_eq!(game.to_play(), Color::Black);
+}
+
+#[test]
+fn older_files_with_variations() {
+    let record: GameRecord = read_sample("variations.sgf").parse().unwrap();
+
+    assert_eq!(record.size, 13);
+    assert_eq!(record.komi, 6.5);
+    assert_eq!(record.white_setup, vec![vertex("K11")]);
+
+    let moves: Vec<String> = record.moves.iter().map(|mv| mv.to_string()).collect();
+    assert_eq!(moves,
+               vec!["black D4", "white K4", "black D10", "white pass", "black C11", "white pass"]);
+
+    let reparsed: GameRecord = record.to_string().parse().unwrap();
+    assert_eq!(reparsed, record);
+}
+
+#[test]
+fn loadsgf_and_printsgf() {
+    let mut game = Game::default();
+
+    assert_eq!(game.loadsgf(sample("nine-by-nine.sgf").to_str().unwrap(), Some(5)),
+               Ok(Color::Black));
+    assert_eq!(game.size(), 9);
+    assert_eq!(game.moves().len(), 4);
+
+    assert_eq!(game.loadsgf("no-such-file.sgf", None), Err("cannot load file".to_string()));
+    assert_eq!(game.moves().len(), 4);
+
+    let printed = game.printsgf(None).unwrap();
+    assert_eq!(printed, "\n(;GM[1]FF[4]SZ[9]KM[5.5]\n;B[ee];W[gc];B[cg];W[ec])");
+}